@@ -0,0 +1,168 @@
+//! Benchmark parsing and rendering a large lockfile
+//!
+//! Run with `cargo bench` from the root of this repository.
+
+use cargo_lock::{dependency::graph::EdgeDirection, Dependency, Lockfile};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::{self, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Allocator which keeps track of the number of bytes currently allocated
+struct CountingAllocator;
+
+/// Number of bytes currently allocated
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Maximum number of bytes allocated at once (since the last reset)
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of packages in the generated lockfile
+const PACKAGES: usize = 10_000;
+
+/// Number of packages in the lockfile used to compare the peak memory usage
+/// of parsing and streaming
+const PEAK_PACKAGES: usize = 20_000;
+
+/// Number of times to run each benchmark
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let toml = generate_lockfile(PACKAGES);
+
+    bench("parse", || {
+        toml.parse::<Lockfile>().unwrap();
+    });
+
+    bench("stream", || {
+        for package in Lockfile::stream_packages(toml.as_bytes()) {
+            package.unwrap();
+        }
+    });
+
+    let large_toml = generate_lockfile(PEAK_PACKAGES);
+
+    let parse_peak = peak_memory(|| {
+        large_toml.parse::<Lockfile>().unwrap();
+    });
+
+    let stream_peak = peak_memory(|| {
+        let names = Lockfile::stream_packages(large_toml.as_bytes())
+            .filter(|package| package.as_ref().unwrap().name.as_str().ends_with('7'))
+            .count();
+        assert!(names > 0);
+    });
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let lockfile = toml.parse::<Lockfile>().unwrap();
+    let lockfile_size = ALLOCATED.load(Ordering::Relaxed) - before;
+
+    bench("list", || {
+        let mut out = io::BufWriter::new(io::sink());
+
+        for package in &lockfile.packages {
+            writeln!(out, "- {}", Dependency::from(package)).unwrap();
+        }
+
+        out.flush().unwrap();
+    });
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let tree = lockfile.dependency_tree().unwrap();
+    let tree_size = ALLOCATED.load(Ordering::Relaxed) - before;
+    let index = tree.nodes()[&Dependency::from(lockfile.packages.last().unwrap())];
+
+    bench("tree", || {
+        let mut out = io::BufWriter::new(io::sink());
+        tree.render(&mut out, index, EdgeDirection::Incoming)
+            .unwrap();
+        out.flush().unwrap();
+    });
+
+    println!(
+        "memory used by the parsed lockfile: {} KiB",
+        lockfile_size / 1024
+    );
+    println!(
+        "memory used by the dependency tree: {} KiB",
+        tree_size / 1024
+    );
+    println!(
+        "peak memory used by parsing {} packages: {} KiB",
+        PEAK_PACKAGES,
+        parse_peak / 1024
+    );
+    println!(
+        "peak memory used by streaming {} packages: {} KiB",
+        PEAK_PACKAGES,
+        stream_peak / 1024
+    );
+}
+
+/// Measure the maximum number of bytes allocated at once by the given
+/// function (on top of what's allocated before calling it)
+fn peak_memory(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    f();
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+/// Run the given benchmark, printing the average time per iteration
+fn bench(name: &str, mut f: impl FnMut()) {
+    let mut total = Duration::default();
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+
+    println!("{:>8}: {:?} / iteration", name, total / ITERATIONS);
+}
+
+/// Generate a V2 lockfile with `len` packages, where each package depends on
+/// up to three packages which follow it (so the last package is depended on,
+/// directly or indirectly, by all of the others)
+fn generate_lockfile(len: usize) -> String {
+    let mut toml = String::new();
+
+    for i in 0..len {
+        toml.push_str(&format!(
+            "[[package]]\nname = \"pkg{}\"\nversion = \"0.1.0\"\n",
+            i
+        ));
+
+        let deps = [i + 1, i + 7, i + 31]
+            .iter()
+            .filter(|&&dep| dep < len)
+            .map(|dep| format!("\"pkg{}\"", dep))
+            .collect::<Vec<_>>();
+
+        if !deps.is_empty() {
+            toml.push_str(&format!("dependencies = [{}]\n", deps.join(", ")));
+        }
+
+        toml.push('\n');
+    }
+
+    toml
+}
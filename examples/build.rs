@@ -0,0 +1,66 @@
+//! Build a `Cargo.lock` file from scratch and serialize it
+//!
+//! Run with `cargo run --example build`.
+
+use cargo_lock::{Dependency, Lockfile, Metadata, Package, Patch, ResolveVersion, Version};
+
+fn main() {
+    let serde = Package {
+        name: "serde".parse().unwrap(),
+        version: Version::parse("1.0.104").unwrap(),
+        source: Some(
+            "registry+https://github.com/rust-lang/crates.io-index"
+                .parse()
+                .unwrap(),
+        ),
+        checksum: Some(
+            "414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449"
+                .parse()
+                .unwrap(),
+        ),
+        dependencies: vec![],
+        replace: None,
+    };
+
+    let app = Package {
+        name: "app".parse().unwrap(),
+        version: Version::parse("0.1.0").unwrap(),
+        source: None,
+        checksum: None,
+        dependencies: vec![Dependency::from(&serde)],
+        replace: None,
+    };
+
+    let lockfile = Lockfile {
+        version: ResolveVersion::V2,
+        packages: vec![app, serde],
+        root: None,
+        metadata: Metadata::default(),
+        patch: Patch::default(),
+        preamble: None,
+    };
+
+    let mut output = Vec::new();
+    lockfile.to_writer_pretty(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let expected = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "serde",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.104"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449"
+"#;
+
+    assert_eq!(output, expected);
+    assert_eq!(output.parse::<Lockfile>().unwrap(), lockfile);
+    print!("{}", output);
+}
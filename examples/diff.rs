@@ -0,0 +1,33 @@
+//! Compare the packages in two `Cargo.lock` files
+//!
+//! Run with `cargo run --example diff` from the root of this repository.
+
+use cargo_lock::{Dependency, Lockfile};
+use std::collections::BTreeSet;
+
+/// Get the set of packages in a lockfile, identified by name, version, and source
+fn package_set(lockfile: &Lockfile) -> BTreeSet<Dependency> {
+    lockfile.packages.iter().map(Dependency::from).collect()
+}
+
+fn main() {
+    let old = Lockfile::load("Cargo.lock").unwrap();
+
+    // Simulate removing a dependency: drop `url` from the new lockfile
+    let mut new = old.clone();
+    new.packages.retain(|pkg| pkg.name.as_str() != "url");
+
+    let old_packages = package_set(&old);
+    let new_packages = package_set(&new);
+
+    let added = new_packages.difference(&old_packages).collect::<Vec<_>>();
+    let removed = old_packages.difference(&new_packages).collect::<Vec<_>>();
+
+    assert!(added.is_empty());
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].name.as_str(), "url");
+
+    for package in removed {
+        println!("- {}", package);
+    }
+}
@@ -0,0 +1,34 @@
+//! Load a `Cargo.lock` file and summarize its packages
+//!
+//! Run with `cargo run --example load` from the root of this repository.
+
+use cargo_lock::{Lockfile, ResolveVersion};
+
+fn main() {
+    let lockfile = Lockfile::load("Cargo.lock").unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V2);
+
+    let package = lockfile
+        .packages
+        .iter()
+        .find(|pkg| pkg.name.as_str() == "cargo-lock")
+        .expect("lockfile should contain this crate");
+
+    // Path dependencies (such as this crate itself) have no source or checksum
+    assert!(package.source.is_none());
+    assert!(package.checksum.is_none());
+
+    let registry_packages = lockfile
+        .packages
+        .iter()
+        .filter(|pkg| pkg.source.is_some())
+        .count();
+
+    assert_eq!(registry_packages + 1, lockfile.packages.len());
+
+    println!(
+        "{} packages ({} from registries)",
+        lockfile.packages.len(),
+        registry_packages
+    );
+}
@@ -0,0 +1,34 @@
+//! Render the reverse dependencies of a package as a tree
+//!
+//! Run with `cargo run --example tree` from the root of this repository.
+
+use cargo_lock::{dependency::graph::EdgeDirection, Dependency, Lockfile};
+
+fn main() {
+    let lockfile = Lockfile::load("Cargo.lock").unwrap();
+    let tree = lockfile.dependency_tree().unwrap();
+
+    let package = lockfile
+        .packages
+        .iter()
+        .find(|pkg| pkg.name.as_str() == "semver-parser")
+        .unwrap();
+
+    let mut output = Vec::new();
+    tree.render(
+        &mut output,
+        tree.nodes()[&Dependency::from(package)],
+        EdgeDirection::Incoming,
+    )
+    .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let expected = "\
+semver-parser 0.7.0
+└── semver 0.9.0
+    └── cargo-lock 4.0.1
+";
+
+    assert_eq!(output, expected);
+    print!("{}", output);
+}
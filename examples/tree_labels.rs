@@ -0,0 +1,51 @@
+//! Render the reverse dependencies of a package as a tree, with custom labels
+//! which show where each package comes from
+//!
+//! Run with `cargo run --example tree_labels` from the root of this repository.
+
+use cargo_lock::{
+    dependency::{graph::EdgeDirection, tree::default_label},
+    Dependency, Lockfile, Package,
+};
+
+/// Label packages with their name, version, and registry (if any)
+fn label(package: &Package) -> String {
+    match &package.source {
+        Some(source) => format!(
+            "{} [{}]",
+            default_label(package),
+            source.display_registry_name()
+        ),
+        None => format!("{} [local]", default_label(package)),
+    }
+}
+
+fn main() {
+    let lockfile = Lockfile::load("Cargo.lock").unwrap();
+    let tree = lockfile.dependency_tree().unwrap();
+
+    let package = lockfile
+        .packages
+        .iter()
+        .find(|pkg| pkg.name.as_str() == "semver-parser")
+        .unwrap();
+
+    let mut output = Vec::new();
+    tree.render_with(
+        &mut output,
+        tree.nodes()[&Dependency::from(package)],
+        EdgeDirection::Incoming,
+        label,
+    )
+    .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let expected = "\
+semver-parser 0.7.0 [crates.io]
+└── semver 0.9.0 [crates.io]
+    └── cargo-lock 4.0.1 [local]
+";
+
+    assert_eq!(output, expected);
+    print!("{}", output);
+}
@@ -2,17 +2,36 @@
 
 #![forbid(unsafe_code)]
 #![warn(rust_2018_idioms, unused_qualifications)]
+// `is_some_and`/`is_none_or` and `#[default]` on enums are newer than our MSRV
+#![allow(clippy::unnecessary_map_or, clippy::derivable_impls)]
 
-use cargo_lock::{package, Dependency, Lockfile, ResolveVersion};
+use cargo_lock::{
+    manifest::{DependencyKind, Unsatisfied},
+    package::{self, version::is_compatible},
+    Dependency, Lockfile, LockfileDiff, Manifest, Package, PackageId, ResolveVersion,
+    SerializeOptions, SourceId, SourceKind,
+};
 use gumdrop::Options;
 use std::{
-    env, fs, io,
+    collections::BTreeSet,
+    env, fs,
+    io::{self, Read, Write},
     path::{Path, PathBuf},
     process::exit,
+    str::FromStr,
 };
 
+#[cfg(any(feature = "http", feature = "cyclonedx"))]
+use url::Url;
+
+#[cfg(feature = "cyclonedx")]
+use cargo_lock::SbomOptions;
+
 #[cfg(feature = "dependency-tree")]
-use cargo_lock::dependency::graph::EdgeDirection;
+use cargo_lock::dependency::{
+    graph::{EdgeDirection, Graph, NodeIndex},
+    tree::{RenderOptions, Symbols, DEFAULT_MAX_DEPTH},
+};
 
 /// Wrapper toplevel command for the `cargo lock` subcommand
 #[derive(Options)]
@@ -32,10 +51,40 @@ enum Command {
     #[options(help = "translate a Cargo.toml file")]
     Translate(TranslateCmd),
 
+    /// The `cargo lock check` subcommand
+    #[options(help = "check a Cargo.lock file for problems")]
+    Check(CheckCmd),
+
+    /// The `cargo lock diff` subcommand
+    #[options(help = "compare two Cargo.lock files")]
+    Diff(DiffCmd),
+
+    /// The `cargo lock dedupe` subcommand
+    #[options(help = "report crates present in more than one version")]
+    Dedupe(DedupeCmd),
+
+    /// The `cargo lock stats` subcommand
+    #[options(help = "print summary statistics about a Cargo.lock file")]
+    Stats(StatsCmd),
+
+    /// The `cargo lock version` subcommand
+    #[options(help = "print the resolve version of a Cargo.lock file")]
+    Version(VersionCmd),
+
+    /// The `cargo lock sbom` subcommand
+    #[cfg(feature = "cyclonedx")]
+    #[options(help = "generate a CycloneDX SBOM from a Cargo.lock file")]
+    Sbom(SbomCmd),
+
     /// The `cargo lock tree` subcommand
     #[cfg(feature = "dependency-tree")]
     #[options(help = "print a dependency tree for the given dependency")]
     Tree(TreeCmd),
+
+    /// The `cargo lock why` subcommand
+    #[cfg(feature = "dependency-tree")]
+    #[options(help = "explain why a package is included in Cargo.lock")]
+    Why(WhyCmd),
 }
 
 /// The `cargo lock list` subcommand
@@ -44,17 +93,336 @@ struct ListCmd {
     /// Input `Cargo.lock` file
     #[options(short = "f", help = "input Cargo.lock file to translate")]
     file: Option<PathBuf>,
+
+    /// Input `Cargo.toml` file of the root package
+    #[options(
+        short = "m",
+        help = "Cargo.toml of the root package (used by --no-dev/--no-build)"
+    )]
+    manifest: Option<PathBuf>,
+
+    /// Exclude packages only used by dev-dependencies of the root package
+    #[options(
+        no_short,
+        help = "exclude packages only used by dev-dependencies (requires Cargo.toml)"
+    )]
+    no_dev: bool,
+
+    /// Exclude packages only used by build-dependencies of the root package
+    #[options(
+        no_short,
+        help = "exclude packages only used by build-dependencies (requires Cargo.toml)"
+    )]
+    no_build: bool,
+
+    /// Show the filesystem locations of path dependencies
+    #[options(no_short, help = "show the filesystem locations of path dependencies")]
+    paths: bool,
+
+    /// Only list packages from git repositories
+    #[options(no_short, help = "only list packages from git repositories")]
+    git: bool,
+
+    /// Only list packages from local paths (including workspace members)
+    #[options(
+        no_short,
+        help = "only list path packages (including workspace members)"
+    )]
+    path: bool,
+
+    /// Only list packages from registries
+    #[options(no_short, help = "only list packages from registries")]
+    registry: bool,
+
+    /// Output packages as JSON
+    #[options(no_short, help = "output packages as a JSON array")]
+    json: bool,
+
+    /// Warn about crates present in more than one version (the default)
+    #[options(
+        no_short,
+        help = "warn on STDERR about crates with multiple versions (the default)"
+    )]
+    warn_duplicates: bool,
+
+    /// Don't warn about crates present in more than one version
+    #[options(no_short, help = "don't warn about crates with multiple versions")]
+    no_warn_duplicates: bool,
 }
 
 impl ListCmd {
     /// Display dependency summary from `Cargo.lock`
     pub fn run(&self) {
-        for package in &load_lockfile(&self.file).packages {
-            println!("- {}", Dependency::from(package));
+        if self.warn_duplicates && self.no_warn_duplicates {
+            eprintln!("*** error: --warn-duplicates and --no-warn-duplicates are exclusive");
+            exit(1);
+        }
+
+        let lockfile = load_lockfile(&self.file);
+
+        let packages = if self.no_dev || self.no_build {
+            let mut kinds = vec![DependencyKind::Normal];
+
+            if !self.no_dev {
+                kinds.push(DependencyKind::Dev);
+            }
+
+            if !self.no_build {
+                kinds.push(DependencyKind::Build);
+            }
+
+            lockfile
+                .packages_for_dependency_kinds(&load_manifest(&self.manifest), &kinds)
+                .unwrap_or_else(|e| {
+                    eprintln!("*** error: {}", e);
+                    exit(1);
+                })
+        } else {
+            lockfile.packages.iter().collect()
+        };
+
+        let packages = if self.git || self.path || self.registry {
+            packages
+                .into_iter()
+                .filter(|package| self.source_filter(package))
+                .collect()
+        } else {
+            packages
+        };
+
+        // Path dependencies have no source in `Cargo.lock`, so their
+        // locations are determined from the root package's manifest if found
+        let manifest = if self.paths {
+            self.manifest_path().map(|path| {
+                (
+                    path.parent().unwrap().to_owned(),
+                    load_manifest(&Some(path)),
+                )
+            })
+        } else {
+            None
+        };
+
+        let mut out = stdout_writer();
+
+        let result = if self.json {
+            self.print_packages_json(&mut out, &packages, manifest.as_ref())
+        } else {
+            let included = packages
+                .iter()
+                .map(|package| PackageId::from(*package))
+                .collect::<BTreeSet<_>>();
+
+            let summaries =
+                lockfile.summaries_filtered(|package| included.contains(&PackageId::from(package)));
+
+            self.print_summaries(&mut out, &summaries, manifest.as_ref())
+        };
+
+        result
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+
+        if !self.no_warn_duplicates {
+            warn_duplicates(&lockfile);
+        }
+    }
+
+    /// Does the given package's source match any of the `--git`, `--path`,
+    /// or `--registry` flags?
+    fn source_filter(&self, package: &Package) -> bool {
+        match package.source.as_ref().map(SourceId::kind) {
+            None | Some(SourceKind::Path) => self.path,
+            Some(SourceKind::Git) => self.git,
+            Some(SourceKind::CratesIo)
+            | Some(SourceKind::AlternateRegistry)
+            | Some(SourceKind::Local) => self.registry,
+        }
+    }
+
+    /// Print the given package summaries (see [`Lockfile::summaries`]), one
+    /// per line
+    fn print_summaries(
+        &self,
+        out: &mut impl Write,
+        summaries: &[Dependency],
+        manifest: Option<&(PathBuf, Manifest)>,
+    ) -> io::Result<()> {
+        for summary in summaries {
+            write!(out, "- {}", summary)?;
+
+            if self.paths {
+                if let Some(path) = package_path(summary, manifest) {
+                    write!(out, " @ {}", display_path(&path))?;
+                }
+            }
+
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the given packages as a JSON array of objects with their name,
+    /// version, source, and checksum (and path with `--paths`)
+    fn print_packages_json(
+        &self,
+        out: &mut impl Write,
+        packages: &[&Package],
+        manifest: Option<&(PathBuf, Manifest)>,
+    ) -> io::Result<()> {
+        /// Encode an optional value as a JSON string (or `null`)
+        fn optional(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| "null".to_owned(), |v| json_string(&v.to_string()))
+        }
+
+        write!(out, "[")?;
+
+        for (i, package) in packages.iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(
+                out,
+                "\n  {{\"name\": {}, \"version\": {}, \"source\": {}, \"checksum\": {}",
+                json_string(package.name.as_str()),
+                json_string(&package.version.to_string()),
+                optional(package.source.as_ref()),
+                optional(package.checksum.as_ref()),
+            )?;
+
+            if self.paths {
+                let path = package_path(&Dependency::from(*package), manifest)
+                    .map(|path| absolute_path(&path));
+                write!(
+                    out,
+                    ", \"path\": {}",
+                    optional(path.map(|p| p.display().to_string()))
+                )?;
+            }
+
+            write!(out, "}}")?;
+        }
+
+        writeln!(out, "\n]")
+    }
+
+    /// Get the path to the manifest, defaulting to a `Cargo.toml` alongside
+    /// the lockfile (if it exists)
+    fn manifest_path(&self) -> Option<PathBuf> {
+        self.manifest.clone().or_else(|| {
+            let manifest_path = lockfile_path(&self.file).with_file_name("Cargo.toml");
+            if manifest_path.exists() {
+                Some(manifest_path)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Get the location of a path dependency, either from its source or from
+/// the given manifest (if it's the root package or one of its direct path
+/// dependencies)
+fn package_path(package: &Dependency, manifest: Option<&(PathBuf, Manifest)>) -> Option<PathBuf> {
+    match &package.source {
+        Some(source) => source.as_path(),
+        None => {
+            let (dir, manifest) = manifest?;
+
+            if manifest.package.as_ref() == Some(&package.name) {
+                return Some(dir.clone());
+            }
+
+            manifest
+                .dependencies
+                .iter()
+                .find(|dep| dep.package_name() == &package.name && dep.path.is_some())
+                .map(|dep| dir.join(dep.path.as_ref().unwrap()))
         }
     }
 }
 
+/// Display a path both relative to the current directory and as an
+/// absolute path
+fn display_path(path: &Path) -> String {
+    let cwd = env::current_dir().unwrap_or_default();
+    let absolute = absolute_path(path);
+
+    match absolute.strip_prefix(&cwd) {
+        Ok(relative) if relative.as_os_str().is_empty() => format!(". ({})", absolute.display()),
+        Ok(relative) => format!("{} ({})", relative.display(), absolute.display()),
+        Err(_) => absolute.display().to_string(),
+    }
+}
+
+/// Get the absolute path for a path relative to the current directory
+fn absolute_path(path: &Path) -> PathBuf {
+    let cwd = env::current_dir().unwrap_or_default();
+    fs::canonicalize(cwd.join(path)).unwrap_or_else(|_| cwd.join(path))
+}
+
+/// Find the (first) package with the given name, tolerating differences in
+/// case and between hyphens and underscores if unambiguous, and otherwise
+/// exiting with an error suggesting the names which were meant
+#[cfg(feature = "dependency-tree")]
+fn find_package<'a>(lockfile: &'a Lockfile, name: &package::Name) -> &'a Package {
+    let packages = lockfile.find_package_fuzzy(name.as_str());
+
+    let mut names = packages
+        .iter()
+        .map(|pkg| format!("`{}`", pkg.name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    match names.len() {
+        0 => eprintln!("*** error: invalid dependency name: `{}`", name),
+        1 => return packages[0],
+        _ => eprintln!(
+            "*** error: invalid dependency name: `{}` (did you mean {}?)",
+            name,
+            names.join(" or ")
+        ),
+    }
+
+    exit(1);
+}
+
+/// Print a warning listing the crates present in more than one version (if
+/// any), naming at most the first few of them
+fn warn_duplicates(lockfile: &Lockfile) {
+    const MAX_NAMES: usize = 5;
+
+    let duplicates = lockfile.find_duplicates();
+
+    if duplicates.is_empty() {
+        return;
+    }
+
+    let mut names = duplicates
+        .keys()
+        .take(MAX_NAMES)
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>();
+
+    if duplicates.len() > MAX_NAMES {
+        names.push("...");
+    }
+
+    let count = match duplicates.len() {
+        1 => "1 crate".to_owned(),
+        n => format!("{} crates", n),
+    };
+
+    eprintln!(
+        "*** warning: {} resolved to multiple versions ({})",
+        count,
+        names.join(", ")
+    );
+}
+
 /// The `cargo lock translate` subcommand
 #[derive(Debug, Options)]
 struct TranslateCmd {
@@ -67,13 +435,43 @@ struct TranslateCmd {
     output: Option<PathBuf>,
 
     /// Cargo.lock format version to translate to
-    #[options(short = "v", help = "Cargo.lock resolve version to output")]
+    #[options(
+        short = "v",
+        help = "Cargo.lock resolve version to output (default: the input's own version)"
+    )]
     version: Option<ResolveVersion>,
+
+    /// Omit blank lines from the output
+    #[options(
+        no_short,
+        help = "omit blank lines (not Cargo's formatting, but parsed the same)"
+    )]
+    compact: bool,
+
+    /// Translate to an older format even if that loses information
+    #[options(
+        no_short,
+        help = "translate to an older format even if that loses information"
+    )]
+    force: bool,
+
+    /// Check that the input is already in canonical form instead
+    #[options(
+        no_short,
+        help = "exit with an error (printing a diff) unless the input is already in canonical form"
+    )]
+    check: bool,
 }
 
 impl TranslateCmd {
-    /// Translate `Cargo.lock` to a different format version
+    /// Translate `Cargo.lock` to a different format version (or just
+    /// reformat it in its own version, unless `--version` is given)
     pub fn run(&self) {
+        if self.check {
+            self.check_canonical();
+            return;
+        }
+
         let output = self
             .output
             .as_ref()
@@ -81,99 +479,1335 @@ impl TranslateCmd {
             .unwrap_or_else(|| Path::new("-"));
 
         let mut lockfile = load_lockfile(&self.file);
+        let version = self.version.unwrap_or(lockfile.version);
+        self.set_version(&mut lockfile, version);
 
-        lockfile.version = self.version.unwrap_or_default();
-        let lockfile_toml = lockfile.to_string();
+        let options = SerializeOptions {
+            compact: self.compact,
+            ..SerializeOptions::default()
+        };
 
         if output == Path::new("-") {
-            println!("{}", &lockfile_toml);
+            let mut out = stdout_writer();
+            lockfile
+                .to_writer_with_options(&mut out, &options)
+                .and_then(|()| out.flush())
+                .unwrap_or_else(|e| exit_on_write_error(e));
         } else {
-            fs::write(output, lockfile_toml.as_bytes()).unwrap_or_else(|e| {
+            let result = fs::File::create(output).and_then(|file| {
+                let mut out = io::BufWriter::new(file);
+                lockfile.to_writer_with_options(&mut out, &options)?;
+                out.flush()
+            });
+
+            result.unwrap_or_else(|e| {
                 eprintln!("*** error: {}", e);
                 exit(1);
             });
         }
     }
+
+    /// Compare the input byte-for-byte with its canonical serialization (in
+    /// its own resolve version unless `--version` is given), printing a diff
+    /// and exiting with an error if they differ
+    fn check_canonical(&self) {
+        if self.output.is_some() {
+            eprintln!("*** error: --check doesn't write any output");
+            exit(1);
+        }
+
+        let path = lockfile_path(&self.file);
+
+        let original = if path == Path::new("-") {
+            let mut original = String::new();
+            io::stdin().read_to_string(&mut original).map(|_| original)
+        } else {
+            fs::read_to_string(&path)
+        };
+
+        let original = original.unwrap_or_else(|e| {
+            eprintln!("*** error: couldn't read {}: {}", path.display(), e);
+            exit(1);
+        });
+
+        let mut lockfile = original.parse::<Lockfile>().unwrap_or_else(|e| {
+            eprintln!("*** error: {}", e);
+            exit(1);
+        });
+
+        if let Some(version) = self.version {
+            self.set_version(&mut lockfile, version);
+        }
+
+        let options = SerializeOptions {
+            compact: self.compact,
+            ..SerializeOptions::default()
+        };
+
+        let canonical = lockfile.to_string_with_options(&options);
+
+        if original == canonical {
+            return;
+        }
+
+        let mut out = stdout_writer();
+        let name = path.display().to_string();
+
+        print_text_diff(
+            &mut out,
+            &name,
+            &format!("{} (canonical)", name),
+            &original,
+            &canonical,
+        )
+        .and_then(|()| out.flush())
+        .unwrap_or_else(|e| exit_on_write_error(e));
+
+        exit(1);
+    }
+
+    /// Change the resolve version of the given lockfile, exiting with an
+    /// error if that loses information (unless `--force` is given)
+    fn set_version(&self, lockfile: &mut Lockfile, version: ResolveVersion) {
+        if let Err(e) = lockfile.set_resolve_version(version) {
+            for loss in &e.losses {
+                eprintln!("*** warning: {}", loss);
+            }
+
+            if !self.force {
+                eprintln!(
+                    "*** error: translating to V{} loses information (use --force to translate anyway)",
+                    version
+                );
+                exit(1);
+            }
+
+            lockfile.version = version;
+        }
+    }
 }
 
-/// The `cargo lock tree` subcommand
-#[cfg(feature = "dependency-tree")]
+/// Print a unified diff (with a single hunk) of two texts which differ.
+///
+/// Lines are compared including their line endings, so a missing newline at
+/// the end of either text counts as a difference, which is marked the same
+/// way as `diff` and `git diff` do.
+fn print_text_diff(
+    out: &mut impl Write,
+    old_name: &str,
+    new_name: &str,
+    old: &str,
+    new: &str,
+) -> io::Result<()> {
+    let old = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new = new.split_inclusive('\n').collect::<Vec<_>>();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_lines = &old[prefix..old.len() - suffix];
+    let new_lines = &new[prefix..new.len() - suffix];
+
+    // Empty ranges start at the line before them, like in `diff -u` output
+    let start = |lines: &[&str]| prefix + usize::from(!lines.is_empty());
+
+    writeln!(out, "--- {}", old_name)?;
+    writeln!(out, "+++ {}", new_name)?;
+    writeln!(
+        out,
+        "@@ -{},{} +{},{} @@",
+        start(old_lines),
+        old_lines.len(),
+        start(new_lines),
+        new_lines.len()
+    )?;
+
+    for (marker, lines) in [('-', old_lines), ('+', new_lines)] {
+        for line in lines {
+            writeln!(out, "{}{}", marker, line.trim_end_matches('\n'))?;
+
+            if !line.ends_with('\n') {
+                writeln!(out, "\\ No newline at end of file")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The `cargo lock check` subcommand
 #[derive(Debug, Options)]
-struct TreeCmd {
+struct CheckCmd {
     /// Input `Cargo.lock` file
-    #[options(short = "f", help = "input Cargo.lock file to translate")]
+    #[options(short = "f", help = "input Cargo.lock file to check")]
     file: Option<PathBuf>,
 
-    /// Dependencies names to draw a tree for
-    #[options(free, help = "dependency names to draw trees for")]
-    dependencies: Vec<package::Name>,
+    /// `Cargo.toml` whose dependencies must be satisfied by `Cargo.lock`
+    #[options(
+        short = "m",
+        help = "Cargo.toml whose dependencies must be satisfied by Cargo.lock"
+    )]
+    manifest: Option<PathBuf>,
+
+    /// Require git dependencies to be pinned to a precise commit
+    #[options(no_short, help = "require git dependencies to be pinned to a commit")]
+    require_git_pins: bool,
+
+    /// Rewrite the lockfile to fix auto-repairable problems
+    #[options(no_short, help = "rewrite Cargo.lock to fix auto-repairable problems")]
+    fix: bool,
+
+    /// Preview the changes `--fix` would make without writing them
+    #[options(no_short, help = "preview the changes --fix would make")]
+    check: bool,
 }
 
-#[cfg(feature = "dependency-tree")]
-impl TreeCmd {
-    /// Display dependency trees from `Cargo.lock`
+impl CheckCmd {
+    /// Check `Cargo.lock` for problems, exiting with an error if any are found
     pub fn run(&self) {
-        let lockfile = load_lockfile(&self.file);
+        let mut lockfile = load_lockfile(&self.file);
+        let mut problems = 0;
 
-        let tree = lockfile.dependency_tree().unwrap_or_else(|e| {
-            eprintln!("*** error: {}", e);
+        if self.fix || self.check {
+            problems += self.fix_problems(&mut lockfile);
+        }
+
+        if let Some((declared, detected)) = lockfile.resolve_version_mismatch() {
+            eprintln!(
+                "*** error: lockfile declares resolve version {} but its contents indicate version {}",
+                declared, detected
+            );
+            problems += 1;
+        }
+
+        for (package, dependency) in lockfile.dangling_dependencies() {
+            eprintln!(
+                "*** error: {} depends on a package missing from the lockfile: {}",
+                PackageId::from(package),
+                dependency
+            );
+            problems += 1;
+        }
+
+        for (package, _) in lockfile.mixed_crates_io_sources() {
+            eprintln!(
+                "*** error: {} {} is present from both the git and sparse crates.io index (use --fix to collapse them)",
+                package.name, package.version
+            );
+            problems += 1;
+        }
+
+        if self.require_git_pins {
+            for package in &lockfile.packages {
+                let is_git = package.source.as_ref().map_or(false, |s| s.is_git());
+
+                if is_git && !package.is_pinned() {
+                    eprintln!(
+                        "*** error: git dependency isn't pinned to a commit: {}",
+                        PackageId::from(package)
+                    );
+                    problems += 1;
+                }
+            }
+        }
+
+        if let Some(path) = &self.manifest {
+            let manifest = load_manifest(&Some(path.clone()));
+
+            for (dependency, reason) in lockfile.unsatisfied_dependencies(&manifest) {
+                let table = match dependency.kind {
+                    DependencyKind::Normal => "dependencies",
+                    DependencyKind::Dev => "dev-dependencies",
+                    DependencyKind::Build => "build-dependencies",
+                };
+
+                match reason {
+                    Unsatisfied::Missing => {
+                        eprint!(
+                            "*** error: no locked version of {} satisfies [{}] {}",
+                            dependency.package_name(),
+                            table,
+                            dependency.name
+                        );
+
+                        match &dependency.req {
+                            Some(req) => eprintln!(" = \"{}\"", req),
+                            None => eprintln!(),
+                        }
+
+                        problems += 1;
+                    }
+                    Unsatisfied::WorkspaceInherited => eprintln!(
+                        "*** warning: can't check [{}] {}: dependencies inherited from the workspace are unsupported",
+                        table, dependency.name
+                    ),
+                }
+            }
+        }
+
+        if problems > 0 {
             exit(1);
-        });
+        }
 
-        // TODO(tarcieri): detect root package(s), automatically use those?
-        if self.dependencies.is_empty() {
-            eprintln!("*** error: no dependency names given");
+        println!("no problems found");
+    }
+
+    /// Fix auto-repairable problems in the given lockfile, writing it back
+    /// unless previewing with `--check`. Returns the number of problems which
+    /// still need fixing, i.e. ones found while previewing.
+    fn fix_problems(&self, lockfile: &mut Lockfile) -> usize {
+        let path = &lockfile_path(&self.file);
+
+        if path == Path::new("-") {
+            eprintln!("*** error: can't fix a lockfile read from STDIN");
             exit(1);
         }
 
-        for (i, dep) in self.dependencies.iter().enumerate() {
-            if i > 0 {
-                println!();
+        let original = fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("*** error: couldn't read {}: {}", path.display(), e);
+            exit(1);
+        });
+
+        let mut fixes = vec![];
+
+        if !original.starts_with("# This file is automatically @generated by Cargo.") {
+            // Replace any other leading comments with the standard header
+            lockfile.preamble = None;
+            fixes.push("added the @generated header".to_owned());
+        }
+
+        if lockfile.version != ResolveVersion::V1 {
+            let moved = lockfile.inline_metadata_checksums();
+
+            if moved > 0 {
+                fixes.push(format!(
+                    "moved {} checksum(s) from [metadata] into [[package]] entries",
+                    moved
+                ));
             }
+        }
 
-            let package = lockfile
-                .packages
-                .iter()
-                .find(|pkg| pkg.name == *dep)
-                .unwrap_or_else(|| {
-                    eprintln!("*** error: invalid dependency name: `{}`", dep);
-                    exit(1);
-                });
+        let collapsed = lockfile.collapse_mixed_crates_io_sources();
 
-            let index = tree.nodes()[&package.into()];
-            tree.render(&mut io::stdout(), index, EdgeDirection::Incoming)
-                .unwrap();
+        if collapsed > 0 {
+            fixes.push(format!(
+                "collapsed {} package(s) present from both the git and sparse crates.io index",
+                collapsed
+            ));
+        }
+
+        if !lockfile
+            .packages
+            .windows(2)
+            .all(|pair| PackageId::from(&pair[0]) <= PackageId::from(&pair[1]))
+        {
+            fixes.push("sorted packages".to_owned());
+        }
+
+        let mut fixed = Vec::new();
+        lockfile.to_writer_pretty(&mut fixed).unwrap();
+
+        if fixed == original.as_bytes() {
+            return 0;
+        }
+
+        if fixes.is_empty() {
+            fixes.push("normalized formatting (e.g. removed empty sections)".to_owned());
+        }
+
+        if self.check {
+            for fix in &fixes {
+                eprintln!("*** error: would fix: {}", fix);
+            }
+
+            return fixes.len();
+        }
+
+        // Write to a temporary file first, so the lockfile is replaced atomically
+        let tmp_path = path.with_extension("lock.tmp");
+
+        fs::write(&tmp_path, &fixed)
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .unwrap_or_else(|e| {
+                eprintln!("*** error: couldn't write {}: {}", path.display(), e);
+                let _ = fs::remove_file(&tmp_path);
+                exit(1);
+            });
+
+        for fix in &fixes {
+            println!("fixed: {}", fix);
         }
+
+        0
     }
 }
 
-/// Load a lockfile from the given path (or `Cargo.toml`)
-fn load_lockfile(path: &Option<PathBuf>) -> Lockfile {
-    let path = path
-        .as_ref()
-        .map(AsRef::as_ref)
-        .unwrap_or_else(|| Path::new("Cargo.lock"));
+/// The `cargo lock dedupe` subcommand
+#[derive(Debug, Options)]
+struct DedupeCmd {
+    /// Input `Cargo.lock` file
+    #[options(short = "f", help = "input Cargo.lock file")]
+    file: Option<PathBuf>,
 
-    Lockfile::load(path).unwrap_or_else(|e| {
-        eprintln!("*** error: {}", e);
-        exit(1);
-    })
+    /// Only report duplicates, without suggesting how to unify them
+    #[options(no_short, help = "only report duplicates (e.g. for CI)")]
+    check: bool,
 }
 
-fn main() {
-    let args = env::args().collect::<Vec<_>>();
+impl DedupeCmd {
+    /// Report crates present in more than one version, exiting with an error
+    /// if there are any
+    pub fn run(&self) {
+        let lockfile = load_lockfile(&self.file);
+        let duplicates = lockfile.find_duplicates();
 
-    let CargoLock::Lock(cmd) = CargoLock::parse_args_default(&args[1..]).unwrap_or_else(|e| {
-        eprintln!("*** error: {}", e);
-        eprintln!("USAGE:");
-        eprintln!("{}", Command::usage());
+        if duplicates.is_empty() {
+            println!("no duplicate packages found");
+            return;
+        }
+
+        let mut out = stdout_writer();
+
+        for (name, packages) in &duplicates {
+            self.print_duplicate(&mut out, name, packages)
+                .unwrap_or_else(|e| exit_on_write_error(e));
+        }
+
+        out.flush().unwrap_or_else(|e| exit_on_write_error(e));
         exit(1);
-    });
+    }
 
-    match cmd {
-        Command::List(list) => list.run(),
-        Command::Translate(translate) => translate.run(),
-        #[cfg(feature = "dependency-tree")]
-        Command::Tree(tree) => tree.run(),
+    /// Print the packages of a duplicated crate, along with the versions
+    /// which could be unified (unless only checking)
+    fn print_duplicate(
+        &self,
+        out: &mut impl Write,
+        name: &package::Name,
+        packages: &[&Package],
+    ) -> io::Result<()> {
+        writeln!(out, "{}:", name)?;
+
+        for package in packages {
+            match &package.source {
+                Some(source) => writeln!(out, "  - {} ({})", package.version, source)?,
+                None => writeln!(out, "  - {}", package.version)?,
+            }
+        }
+
+        if self.check {
+            return Ok(());
+        }
+
+        // Packages are sorted by version, so semver-compatible versions are
+        // grouped together
+        let mut versions = packages.iter().map(|pkg| &pkg.version).collect::<Vec<_>>();
+        versions.dedup();
+
+        let mut start = 0;
+
+        for end in 1..=versions.len() {
+            if end < versions.len() && is_compatible(versions[end - 1], versions[end]) {
+                continue;
+            }
+
+            let group = &versions[start..end];
+            start = end;
+
+            if let [_, .., latest] = group {
+                let group = group.iter().map(ToString::to_string).collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "  could be unified: {} -> {}",
+                    group.join(", "),
+                    latest
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The `cargo lock stats` subcommand
+#[derive(Debug, Options)]
+struct StatsCmd {
+    /// Input `Cargo.lock` file
+    #[options(short = "f", help = "input Cargo.lock file")]
+    file: Option<PathBuf>,
+
+    /// Output statistics as JSON
+    #[options(no_short, help = "output statistics as a JSON object")]
+    json: bool,
+}
+
+impl StatsCmd {
+    /// Print summary statistics about `Cargo.lock`
+    pub fn run(&self) {
+        let lockfile = load_lockfile(&self.file);
+        let metrics = lockfile.metrics();
+
+        // Number of packages from crates.io, git, paths, and other sources
+        let mut sources = [0; 4];
+
+        for (kind, packages) in lockfile.packages_by_source_kind() {
+            let index = match kind {
+                SourceKind::CratesIo => 0,
+                SourceKind::Git => 1,
+                SourceKind::Path => 2,
+                SourceKind::AlternateRegistry | SourceKind::Local => 3,
+            };
+
+            sources[index] += packages.len();
+        }
+
+        let [crates_io, git, path, other] = sources;
+        let mut out = stdout_writer();
+
+        let result = if self.json {
+            writeln!(
+                out,
+                "{{\"packages\": {}, \"names\": {}, \"duplicates\": {}, \"sources\": \
+                 {{\"crates.io\": {}, \"git\": {}, \"path\": {}, \"other\": {}}}}}",
+                metrics.packages, metrics.names, metrics.duplicates, crates_io, git, path, other
+            )
+        } else {
+            writeln!(out, "packages: {}", metrics.packages)
+                .and_then(|()| writeln!(out, "unique names: {}", metrics.names))
+                .and_then(|()| writeln!(out, "duplicated crates: {}", metrics.duplicates))
+                .and_then(|()| writeln!(out, "sources:"))
+                .and_then(|()| writeln!(out, "  crates.io: {}", crates_io))
+                .and_then(|()| writeln!(out, "  git: {}", git))
+                .and_then(|()| writeln!(out, "  path: {}", path))
+                .and_then(|()| writeln!(out, "  other: {}", other))
+        };
+
+        result
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+    }
+}
+
+/// The `cargo lock version` subcommand
+#[derive(Debug, Options)]
+struct VersionCmd {
+    /// Input `Cargo.lock` file
+    #[options(short = "f", help = "input Cargo.lock file")]
+    file: Option<PathBuf>,
+}
+
+impl VersionCmd {
+    /// Print the resolve version of `Cargo.lock` without fully parsing it
+    pub fn run(&self) {
+        let path = lockfile_path(&self.file);
+
+        let result = if path == Path::new("-") {
+            ResolveVersion::peek(io::stdin())
+        } else {
+            fs::File::open(&path)
+                .map_err(|e| e.into())
+                .and_then(ResolveVersion::peek)
+        };
+
+        let version = result.unwrap_or_else(|e| {
+            eprintln!("*** error: couldn't read {}: {}", path.display(), e);
+            exit(1);
+        });
+
+        let mut out = stdout_writer();
+        writeln!(out, "V{}", version)
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+    }
+}
+
+/// The `cargo lock sbom` subcommand
+#[cfg(feature = "cyclonedx")]
+#[derive(Debug, Options)]
+struct SbomCmd {
+    /// Input `Cargo.lock` file
+    #[options(short = "f", help = "input Cargo.lock file")]
+    file: Option<PathBuf>,
+
+    /// Index to treat as crates.io
+    #[options(
+        no_short,
+        meta = "URL",
+        help = "index URL to treat as crates.io, e.g. a mirror (without `sparse+`)"
+    )]
+    crates_io_index: Option<Url>,
+}
+
+#[cfg(feature = "cyclonedx")]
+impl SbomCmd {
+    /// Print a CycloneDX SBOM for `Cargo.lock`
+    pub fn run(&self) {
+        let lockfile = load_lockfile(&self.file);
+        let mut out = stdout_writer();
+
+        let options = SbomOptions {
+            crates_io_index: self.crates_io_index.clone(),
+            ..SbomOptions::default()
+        };
+
+        out.write_all(lockfile.to_cyclonedx_with_options(&options).as_bytes())
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+    }
+}
+
+/// The `cargo lock diff` subcommand
+#[derive(Debug, Options)]
+struct DiffCmd {
+    /// Old `Cargo.lock` file
+    #[options(
+        no_short,
+        meta = "PATH",
+        help = "old Cargo.lock file to compare against"
+    )]
+    old: Option<String>,
+
+    /// New `Cargo.lock` file
+    #[options(
+        no_short,
+        meta = "PATH",
+        help = "new Cargo.lock file (default: discovered Cargo.lock)"
+    )]
+    new: Option<PathBuf>,
+
+    /// Treat the git and sparse forms of the crates.io index as the same
+    #[options(
+        no_short,
+        help = "treat crates.io sources accessed via git and sparse as the same"
+    )]
+    normalize_sources: bool,
+
+    /// Don't fetch the old lockfile if it's given as a URL
+    #[cfg(feature = "http")]
+    #[options(no_short, help = "don't access the network (i.e. to fetch --old)")]
+    offline: bool,
+}
+
+impl DiffCmd {
+    /// Print the differences between two lockfiles, exiting with an error if
+    /// there are any
+    pub fn run(&self) {
+        let old = self.old.as_ref().unwrap_or_else(|| {
+            eprintln!("*** error: no old lockfile given (use --old)");
+            exit(1);
+        });
+
+        let mut old = self.load_old(old);
+        let mut new = load_lockfile(&self.new);
+
+        if self.normalize_sources {
+            old.normalize_crates_io_sources();
+            new.normalize_crates_io_sources();
+        }
+
+        let diff = old.diff(&new);
+
+        if diff.is_empty() {
+            println!("no differences found");
+            return;
+        }
+
+        let mut out = stdout_writer();
+
+        print_diff(&mut out, &diff)
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+
+        exit(1);
+    }
+
+    /// Load the old lockfile, which may be given as a URL with the `http`
+    /// feature enabled
+    fn load_old(&self, old: &str) -> Lockfile {
+        #[cfg(feature = "http")]
+        {
+            if old.starts_with("http://") || old.starts_with("https://") {
+                // Cargo's `--offline` flag sets `CARGO_NET_OFFLINE` for subcommands
+                let offline = self.offline
+                    || env::var("CARGO_NET_OFFLINE").map_or(false, |value| value == "true");
+
+                if offline {
+                    eprintln!("*** error: can't fetch {} in offline mode", old);
+                    exit(1);
+                }
+
+                let url = Url::parse(old).unwrap_or_else(|e| {
+                    eprintln!("*** error: invalid URL `{}`: {}", old, e);
+                    exit(1);
+                });
+
+                return fetch_lockfile(&url);
+            }
+        }
+
+        load_lockfile(&Some(PathBuf::from(old)))
+    }
+}
+
+/// Download and parse the lockfile at the given `http(s)` URL using the
+/// `curl` command, exiting with an error if that fails
+#[cfg(feature = "http")]
+fn fetch_lockfile(url: &Url) -> Lockfile {
+    /// `User-Agent` to identify requests by
+    const USER_AGENT: &str = concat!(
+        "cargo-lock/",
+        env!("CARGO_PKG_VERSION"),
+        " (https://github.com/rustsec/cargo-lock)"
+    );
+
+    let output = std::process::Command::new("curl")
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--fail")
+        .arg("--location")
+        .arg("--user-agent")
+        .arg(USER_AGENT)
+        .arg(url.as_str())
+        .output()
+        .unwrap_or_else(|e| {
+            eprintln!("*** error: couldn't run curl to fetch {}: {}", url, e);
+            exit(1);
+        });
+
+    if !output.status.success() {
+        eprintln!(
+            "*** error: couldn't fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        exit(1);
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("*** error: couldn't parse {}: {}", url, e);
+            exit(1);
+        })
+}
+
+/// Print the differences between two lockfiles, in sections by kind of change
+fn print_diff(out: &mut impl Write, diff: &LockfileDiff) -> io::Result<()> {
+    /// Display an optional source
+    fn source(source: &Option<SourceId>) -> String {
+        source
+            .as_ref()
+            .map_or_else(|| "(none)".to_owned(), ToString::to_string)
+    }
+
+    let mut sections = 0;
+    let mut section = |out: &mut dyn Write, title: &str, lines: Vec<String>| {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        if sections > 0 {
+            writeln!(out)?;
+        }
+
+        sections += 1;
+        writeln!(out, "{}:", title)?;
+
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+
+        Ok(())
+    };
+
+    section(
+        out,
+        "added",
+        diff.added.iter().map(|id| format!("+ {}", id)).collect(),
+    )?;
+
+    section(
+        out,
+        "removed",
+        diff.removed.iter().map(|id| format!("- {}", id)).collect(),
+    )?;
+
+    section(
+        out,
+        "version changed",
+        diff.version_changed
+            .iter()
+            .map(|change| format!("~ {} {} -> {}", change.name, change.old, change.new))
+            .collect(),
+    )?;
+
+    section(
+        out,
+        "source changed",
+        diff.source_changed
+            .iter()
+            .map(|change| {
+                format!(
+                    "! {} {}: {} -> {}",
+                    change.name,
+                    change.version,
+                    source(&change.old),
+                    source(&change.new)
+                )
+            })
+            .collect(),
+    )
+}
+
+/// The `cargo lock tree` subcommand
+#[cfg(feature = "dependency-tree")]
+#[derive(Debug, Options)]
+struct TreeCmd {
+    /// Input `Cargo.lock` files
+    #[options(
+        short = "f",
+        long = "file",
+        help = "input Cargo.lock file(s) to merge and draw trees for"
+    )]
+    files: Vec<PathBuf>,
+
+    /// Maximum width of package labels
+    #[options(no_short, meta = "N", help = "truncate package labels to N columns")]
+    limit_width: Option<usize>,
+
+    /// Maximum depth to draw trees to
+    #[options(
+        no_short,
+        meta = "N",
+        help = "only draw N levels of the tree below each given dependency"
+    )]
+    depth: Option<usize>,
+
+    /// Direction to draw trees in
+    #[options(
+        no_short,
+        meta = "DIR",
+        help = "`incoming` (packages depending on each given one) or `outgoing` (its dependencies)"
+    )]
+    direction: Option<TreeDirection>,
+
+    /// Characters to draw trees with
+    #[options(
+        no_short,
+        meta = "CHARSET",
+        help = "`unicode` (box-drawing characters, the default) or `ascii`"
+    )]
+    charset: Option<TreeCharset>,
+
+    /// Mark repeated packages instead of silently omitting their dependencies
+    #[options(
+        no_short,
+        help = "show packages whose dependencies were already drawn as `name version (*)`"
+    )]
+    dedupe_subtrees: bool,
+
+    /// Output the dependency graph in Graphviz DOT format
+    #[options(
+        no_short,
+        help = "output the dependency graph (or the given dependency's tree) in Graphviz DOT format"
+    )]
+    dot: bool,
+
+    /// Draw trees for all crates present in more than one version
+    #[options(
+        no_short,
+        help = "draw trees for every crate present in more than one version"
+    )]
+    duplicates: bool,
+
+    /// Package to print the shortest dependency path to
+    #[options(
+        no_short,
+        meta = "NAME",
+        help = "print the shortest dependency path from the given dependency to NAME"
+    )]
+    to: Option<package::Name>,
+
+    /// Dependencies names to draw a tree for
+    #[options(
+        free,
+        help = "dependency names to draw trees for (defaults to the root packages)"
+    )]
+    dependencies: Vec<package::Name>,
+}
+
+#[cfg(feature = "dependency-tree")]
+impl TreeCmd {
+    /// Display dependency trees from `Cargo.lock`
+    pub fn run(&self) {
+        let mut files = self.files.iter();
+        let mut lockfile = load_lockfile(&files.next().cloned());
+
+        for file in files {
+            let other = load_lockfile(&Some(file.clone()));
+
+            lockfile.merge(&other).unwrap_or_else(|e| {
+                eprintln!("*** error: couldn't merge {}: {}", file.display(), e);
+                exit(1);
+            });
+        }
+
+        let tree = lockfile.dependency_tree().unwrap_or_else(|e| {
+            eprintln!("*** error: {}", e);
+            exit(1);
+        });
+
+        // Without dependency names, the whole graph is exported
+        if self.dot && self.dependencies.is_empty() && !self.duplicates {
+            let mut out = stdout_writer();
+            tree.render_dot(&mut out)
+                .and_then(|()| out.flush())
+                .unwrap_or_else(|e| exit_on_write_error(e));
+            return;
+        }
+
+        let options = RenderOptions {
+            max_depth: self.depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            max_width: self.limit_width,
+            symbols: match self.charset.unwrap_or_default() {
+                TreeCharset::Unicode => Symbols::unicode(),
+                TreeCharset::Ascii => Symbols::ascii(),
+            },
+            mark_repeated: self.dedupe_subtrees,
+        };
+
+        if let Some(to) = &self.to {
+            self.print_path(&lockfile, to);
+            return;
+        }
+
+        // Trees are grouped into sections, i.e. all versions of a duplicate
+        let (sections, default_direction) = if self.duplicates {
+            if !self.dependencies.is_empty() {
+                eprintln!("*** error: --duplicates can't be used with dependency names");
+                exit(1);
+            }
+
+            let duplicates = lockfile.find_duplicates();
+
+            if duplicates.is_empty() {
+                println!("no duplicate packages found");
+                return;
+            }
+
+            (duplicates.into_values().collect(), TreeDirection::Incoming)
+        } else if self.dependencies.is_empty() {
+            // Without dependency names, draw what the root packages pull in
+            let roots = lockfile.root_packages().unwrap_or_else(|e| {
+                eprintln!("*** error: {}", e);
+                exit(1);
+            });
+
+            (
+                roots.into_iter().map(|root| vec![root]).collect::<Vec<_>>(),
+                TreeDirection::Outgoing,
+            )
+        } else {
+            let packages = self
+                .dependencies
+                .iter()
+                .map(|dep| vec![find_package(&lockfile, dep)])
+                .collect();
+
+            (packages, TreeDirection::Incoming)
+        };
+
+        let direction = match self.direction.unwrap_or(default_direction) {
+            TreeDirection::Incoming => EdgeDirection::Incoming,
+            TreeDirection::Outgoing => EdgeDirection::Outgoing,
+        };
+
+        let mut out = stdout_writer();
+
+        if self.dot {
+            let package = match sections.concat().as_slice() {
+                [package] => *package,
+                _ => {
+                    eprintln!("*** error: --dot can only export the tree of one package");
+                    exit(1);
+                }
+            };
+
+            let index = tree.nodes()[&package.into()];
+            tree.subgraph(index, direction)
+                .render_dot(&mut out)
+                .and_then(|()| out.flush())
+                .unwrap_or_else(|e| exit_on_write_error(e));
+            return;
+        }
+
+        for (i, packages) in sections.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(out).unwrap_or_else(|e| exit_on_write_error(e));
+            }
+
+            for package in packages {
+                let index = tree.nodes()[&package.into()];
+                tree.render_with_options(&mut out, index, direction, &options)
+                    .unwrap_or_else(|e| exit_on_write_error(e));
+            }
+        }
+
+        out.flush().unwrap_or_else(|e| exit_on_write_error(e));
+    }
+
+    /// Print the shortest dependency path from the (single) given dependency
+    /// to `to`, exiting with an error if there is none
+    fn print_path(&self, lockfile: &Lockfile, to: &package::Name) {
+        let from = match self.dependencies.as_slice() {
+            [from] => from,
+            _ => {
+                eprintln!("*** error: --to requires exactly one dependency name");
+                exit(1);
+            }
+        };
+
+        let path = lockfile
+            .find_path(from, to)
+            .unwrap_or_else(|e| {
+                eprintln!("*** error: {}", e);
+                exit(1);
+            })
+            .unwrap_or_else(|| {
+                eprintln!("*** error: `{}` doesn't depend on `{}`", from, to);
+                exit(1);
+            });
+
+        let path = path
+            .iter()
+            .map(|pkg| format!("{} {}", pkg.name, pkg.version))
+            .collect::<Vec<_>>();
+
+        let mut out = stdout_writer();
+        writeln!(out, "{}", path.join(" -> "))
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+    }
+}
+
+/// Direction to draw dependency trees in
+#[cfg(feature = "dependency-tree")]
+#[derive(Copy, Clone, Debug)]
+enum TreeDirection {
+    /// Draw the packages which depend on the given package
+    Incoming,
+
+    /// Draw the dependencies of the given package
+    Outgoing,
+}
+
+#[cfg(feature = "dependency-tree")]
+impl FromStr for TreeDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "incoming" => Ok(TreeDirection::Incoming),
+            "outgoing" => Ok(TreeDirection::Outgoing),
+            _ => Err(format!(
+                "invalid direction `{}` (expected `incoming` or `outgoing`)",
+                s
+            )),
+        }
+    }
+}
+
+/// Characters to draw dependency trees with
+#[cfg(feature = "dependency-tree")]
+#[derive(Copy, Clone, Debug)]
+enum TreeCharset {
+    /// Unicode box-drawing characters
+    Unicode,
+
+    /// ASCII characters only
+    Ascii,
+}
+
+#[cfg(feature = "dependency-tree")]
+impl Default for TreeCharset {
+    fn default() -> Self {
+        TreeCharset::Unicode
+    }
+}
+
+#[cfg(feature = "dependency-tree")]
+impl FromStr for TreeCharset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "unicode" | "utf8" => Ok(TreeCharset::Unicode),
+            "ascii" => Ok(TreeCharset::Ascii),
+            _ => Err(format!(
+                "invalid charset `{}` (expected `unicode` or `ascii`)",
+                s
+            )),
+        }
+    }
+}
+
+/// The `cargo lock why` subcommand
+#[cfg(feature = "dependency-tree")]
+#[derive(Debug, Options)]
+struct WhyCmd {
+    /// Input `Cargo.lock` file
+    #[options(short = "f", help = "input Cargo.lock file")]
+    file: Option<PathBuf>,
+
+    /// Output dependency paths as JSON
+    #[options(no_short, help = "output dependency paths as a JSON array")]
+    json: bool,
+
+    /// Maximum number of dependency paths to find
+    #[options(no_short, help = "maximum number of paths to show (default 100)")]
+    limit: Option<usize>,
+
+    /// Name of the package to explain
+    #[options(free, help = "name of the package to explain")]
+    package: Option<package::Name>,
+}
+
+#[cfg(feature = "dependency-tree")]
+impl WhyCmd {
+    /// Default maximum number of dependency paths to find
+    const DEFAULT_LIMIT: usize = 100;
+
+    /// Display the dependency paths leading to a package
+    pub fn run(&self) {
+        let name = self.package.as_ref().unwrap_or_else(|| {
+            eprintln!("*** error: no package name given");
+            exit(1);
+        });
+
+        let lockfile = load_lockfile(&self.file);
+
+        let tree = lockfile.dependency_tree().unwrap_or_else(|e| {
+            eprintln!("*** error: {}", e);
+            exit(1);
+        });
+
+        // Look for one more path than shown to tell if there are more
+        let limit = self.limit.unwrap_or(Self::DEFAULT_LIMIT);
+        let max_paths = limit.saturating_add(1);
+        let mut paths = vec![];
+
+        for package in lockfile.packages.iter().filter(|pkg| pkg.name == *name) {
+            if paths.len() >= max_paths {
+                break;
+            }
+
+            let index = tree.nodes()[&package.into()];
+            paths.extend(tree.paths_to(index, Some(max_paths - paths.len())));
+        }
+
+        if paths.is_empty() && !lockfile.packages.iter().any(|pkg| pkg.name == *name) {
+            eprintln!("*** error: invalid package name: `{}`", name);
+            exit(1);
+        }
+
+        if paths.len() > limit {
+            paths.truncate(limit);
+            eprintln!(
+                "*** warning: stopped after finding {} paths (use --limit to find more)",
+                limit
+            );
+        }
+
+        let mut out = stdout_writer();
+
+        self.print_paths(&mut out, tree.graph(), &paths)
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_on_write_error(e));
+    }
+
+    /// Print the given dependency paths, either as text or JSON
+    fn print_paths(
+        &self,
+        out: &mut impl Write,
+        graph: &Graph,
+        paths: &[Vec<NodeIndex>],
+    ) -> io::Result<()> {
+        if self.json {
+            write!(out, "[")?;
+
+            for (i, path) in paths.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",\n ")?;
+                }
+
+                write!(out, "[")?;
+
+                for (j, &index) in path.iter().enumerate() {
+                    if j > 0 {
+                        write!(out, ", ")?;
+                    }
+
+                    let id = PackageId::from(&graph[index]).to_string();
+                    write!(out, "{}", json_string(&id))?;
+                }
+
+                write!(out, "]")?;
+            }
+
+            writeln!(out, "]")?;
+        } else {
+            for path in paths {
+                for (i, &index) in path.iter().enumerate() {
+                    if i > 0 {
+                        write!(out, " -> ")?;
+                    }
+
+                    write!(out, "{} {}", graph[index].name, graph[index].version)?;
+                }
+
+                writeln!(out)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode a string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// Get a buffered writer for STDOUT, so large outputs are streamed
+/// efficiently rather than written line by line
+fn stdout_writer() -> io::BufWriter<io::StdoutLock<'static>> {
+    io::BufWriter::new(io::stdout().lock())
+}
+
+/// Handle an error writing to STDOUT, exiting quietly if it was closed early
+/// (e.g. when piping output into `head`)
+fn exit_on_write_error(e: io::Error) -> ! {
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        exit(0);
+    }
+
+    eprintln!("*** error: {}", e);
+    exit(1);
+}
+
+/// Load a lockfile from the given path (see [`lockfile_path`])
+///
+/// A path of `-` reads the lockfile from STDIN.
+fn load_lockfile(path: &Option<PathBuf>) -> Lockfile {
+    let path = &lockfile_path(path);
+
+    let result = if path == Path::new("-") {
+        let mut toml_string = String::new();
+
+        match io::stdin().read_to_string(&mut toml_string) {
+            Ok(_) => toml_string.parse(),
+            Err(e) => {
+                eprintln!("*** error: couldn't read STDIN: {}", e);
+                exit(1);
+            }
+        }
+    } else {
+        Lockfile::load(path)
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("*** error: {}", e);
+        exit(1);
+    })
+}
+
+/// Get the path of the lockfile to use: the given path if any, otherwise the
+/// `CARGO_LOCK` environment variable if set, and otherwise the `Cargo.lock`
+/// in the current directory or the closest of its ancestors which has one
+/// (like Cargo finds the workspace root). Falls back to `Cargo.lock` if none
+/// is found, so the error refers to it. Given directories are resolved to
+/// the `Cargo.lock` within them.
+fn lockfile_path(path: &Option<PathBuf>) -> PathBuf {
+    let explicit = path.clone().or_else(|| {
+        env::var_os("CARGO_LOCK")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    });
+
+    if let Some(path) = explicit {
+        return if path.is_dir() {
+            path.join("Cargo.lock")
+        } else {
+            path
+        };
+    }
+
+    env::current_dir()
+        .ok()
+        .and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join("Cargo.lock"))
+                .find(|path| path.is_file())
+        })
+        .unwrap_or_else(|| PathBuf::from("Cargo.lock"))
+}
+
+/// Load a manifest from the given path (or `Cargo.toml`)
+fn load_manifest(path: &Option<PathBuf>) -> Manifest {
+    let path = path
+        .as_ref()
+        .map(AsRef::as_ref)
+        .unwrap_or_else(|| Path::new("Cargo.toml"));
+
+    Manifest::load(path).unwrap_or_else(|e| {
+        eprintln!("*** error: {}", e);
+        exit(1);
+    })
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    let CargoLock::Lock(cmd) = CargoLock::parse_args_default(&args[1..]).unwrap_or_else(|e| {
+        eprintln!("*** error: {}", e);
+        eprintln!("USAGE:");
+        eprintln!("{}", Command::usage());
+        exit(1);
+    });
+
+    match cmd {
+        Command::List(list) => list.run(),
+        Command::Translate(translate) => translate.run(),
+        Command::Check(check) => check.run(),
+        Command::Diff(diff) => diff.run(),
+        Command::Dedupe(dedupe) => dedupe.run(),
+        Command::Stats(stats) => stats.run(),
+        Command::Version(version) => version.run(),
+        #[cfg(feature = "cyclonedx")]
+        Command::Sbom(sbom) => sbom.run(),
+        #[cfg(feature = "dependency-tree")]
+        Command::Tree(tree) => tree.run(),
+        #[cfg(feature = "dependency-tree")]
+        Command::Why(why) => why.run(),
     }
 }
@@ -14,6 +14,9 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Package dependencies
+///
+/// Dependencies are ordered by their name, version, and source, i.e. the
+/// same canonical order as the [`Package`]s they refer to.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize)]
 pub struct Dependency {
     /// Name of the dependency
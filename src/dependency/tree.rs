@@ -9,8 +9,11 @@ use super::{
     graph::{EdgeDirection, Graph, NodeIndex, Nodes},
     Dependency,
 };
-use crate::{error::Error, lockfile::Lockfile, Map};
-use std::{collections::BTreeSet as Set, io};
+use crate::{error::Error, lockfile::Lockfile, package::Package, Map};
+use std::{
+    collections::{btree_map::Entry, BTreeSet as Set, VecDeque},
+    io,
+};
 
 /// Dependency tree computed from a `Cargo.lock` file
 #[derive(Clone, Debug)]
@@ -68,7 +71,209 @@ impl Tree {
         direction: EdgeDirection,
         symbols: &Symbols,
     ) -> io::Result<()> {
-        Presenter::new(&self.graph, symbols).print_node(w, node_index, direction)
+        let options = RenderOptions {
+            symbols: *symbols,
+            ..RenderOptions::default()
+        };
+
+        self.render_with_options(w, node_index, direction, &options)
+    }
+
+    /// Render the dependency graph for the given [`NodeIndex`] using the
+    /// provided [`RenderOptions`].
+    pub fn render_with_options(
+        &self,
+        w: &mut impl io::Write,
+        node_index: NodeIndex,
+        direction: EdgeDirection,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        Presenter::new(&self.graph, options).print_node(w, node_index, direction)
+    }
+
+    /// Render the dependency graph for the given [`NodeIndex`] using a custom
+    /// function to format the label of each package, e.g. to include its
+    /// source or checksum, or other annotations. [`default_label`] formats
+    /// labels the same way as [`Tree::render`].
+    pub fn render_with<F: Fn(&Package) -> String>(
+        &self,
+        w: &mut impl io::Write,
+        node_index: NodeIndex,
+        direction: EdgeDirection,
+        label: F,
+    ) -> io::Result<()> {
+        let options = RenderOptions::default();
+        let mut presenter = Presenter::new(&self.graph, &options);
+        presenter.label = Some(&label);
+        presenter.print_node(w, node_index, direction)
+    }
+
+    /// Render the whole dependency graph in the Graphviz DOT language, with
+    /// one node per package (labeled with its name and version) and edges
+    /// pointing from packages to their dependencies.
+    pub fn render_dot(&self, w: &mut impl io::Write) -> io::Result<()> {
+        writeln!(w, "digraph dependencies {{")?;
+
+        // Node IDs are derived from graph indexes, since package names (e.g.
+        // ones containing hyphens) aren't necessarily valid DOT IDs
+        for index in self.graph.node_indices() {
+            write!(w, "    n{} [label=", index.index())?;
+            write_dot_string(w, &default_label(&self.graph[index]))?;
+            writeln!(w, "];")?;
+        }
+
+        for edge in self.graph.raw_edges() {
+            writeln!(
+                w,
+                "    n{} -> n{};",
+                edge.source().index(),
+                edge.target().index()
+            )?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Get the part of this tree reachable from the given [`NodeIndex`] in
+    /// the given direction, i.e. the package and its (transitive)
+    /// dependencies for [`EdgeDirection::Outgoing`], or the packages
+    /// depending on it for [`EdgeDirection::Incoming`].
+    ///
+    /// The subgraph keeps all edges between the packages it contains, so
+    /// rendering it (e.g. using [`Tree::render_dot`]) only draws that
+    /// package's neighborhood. Node indexes differ from the ones in this
+    /// tree, so use [`Tree::nodes`] of the subgraph to look packages up.
+    pub fn subgraph(&self, root: NodeIndex, direction: EdgeDirection) -> Tree {
+        let mut reachable = Set::new();
+        let mut stack = vec![root];
+
+        while let Some(node_index) = stack.pop() {
+            if reachable.insert(node_index) {
+                stack.extend(self.graph.neighbors_directed(node_index, direction));
+            }
+        }
+
+        let graph = self.graph.filter_map(
+            |node_index, package| reachable.contains(&node_index).then(|| package.clone()),
+            |_, dependency| Some(dependency.clone()),
+        );
+
+        let nodes = graph
+            .node_indices()
+            .map(|node_index| (Dependency::from(&graph[node_index]), node_index))
+            .collect();
+
+        Tree { graph, nodes }
+    }
+
+    /// Get paths from root packages (i.e. ones which no other package
+    /// depends upon) to the given [`NodeIndex`], in dependency order.
+    ///
+    /// Paths never contain the same node twice, so cycles are handled. The
+    /// number of paths can grow exponentially with the size of the graph, so
+    /// at most `max_paths` are found if given. Paths are sorted by the
+    /// packages they contain.
+    pub fn paths_to(&self, node_index: NodeIndex, max_paths: Option<usize>) -> Vec<Vec<NodeIndex>> {
+        let mut paths = self.collect_paths(node_index, max_paths.unwrap_or(usize::MAX));
+
+        paths.sort_by(|a, b| {
+            let a = a.iter().map(|&index| &self.graph[index]);
+            let b = b.iter().map(|&index| &self.graph[index]);
+            a.cmp(b)
+        });
+
+        paths
+    }
+
+    /// Collect up to `max_paths` paths from root packages to the given node
+    /// by walking from it towards the roots depth-first. The walk uses an
+    /// explicit stack, so arbitrarily long paths are handled.
+    fn collect_paths(&self, node_index: NodeIndex, max_paths: usize) -> Vec<Vec<NodeIndex>> {
+        let parents = |index| {
+            self.graph
+                .neighbors_directed(index, EdgeDirection::Incoming)
+        };
+
+        let mut paths = vec![];
+
+        if parents(node_index).next().is_none() {
+            paths.push(vec![node_index]);
+            return paths;
+        }
+
+        // The (reversed) path walked so far, along with the parents of each
+        // of its nodes which are yet to be visited
+        let mut path = vec![node_index];
+        let mut on_path = Set::new();
+        on_path.insert(node_index);
+        let mut stack = vec![parents(node_index)];
+
+        while let Some(remaining) = stack.last_mut() {
+            if paths.len() >= max_paths {
+                break;
+            }
+
+            match remaining.next() {
+                Some(parent) if on_path.contains(&parent) => (),
+                Some(parent) => {
+                    path.push(parent);
+
+                    if parents(parent).next().is_none() {
+                        paths.push(path.iter().rev().cloned().collect());
+                        path.pop();
+                    } else {
+                        on_path.insert(parent);
+                        stack.push(parents(parent));
+                    }
+                }
+                None => {
+                    stack.pop();
+                    on_path.remove(&path.pop().unwrap());
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Find the shortest dependency path from `from` to `to`, i.e. a chain
+    /// of packages starting with `from` in which each package depends on the
+    /// next one, ending with `to`. Returns `None` if `from` doesn't depend
+    /// on `to` (directly or transitively).
+    ///
+    /// Packages are visited breadth-first at most once each, so cycles are
+    /// handled.
+    pub fn find_path(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let mut parents = Map::new();
+        parents.insert(from, from);
+
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+
+        while let Some(node_index) = queue.pop_front() {
+            if node_index == to {
+                let mut path = vec![to];
+
+                while *path.last().unwrap() != from {
+                    path.push(parents[path.last().unwrap()]);
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            for child in self
+                .graph
+                .neighbors_directed(node_index, EdgeDirection::Outgoing)
+            {
+                if let Entry::Vacant(entry) = parents.entry(child) {
+                    entry.insert(node_index);
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        None
     }
 
     /// Get the `petgraph` dependency graph.
@@ -82,32 +287,116 @@ impl Tree {
     }
 }
 
+/// Default maximum depth to render dependency trees to.
+///
+/// This is well beyond the depth of any real-world dependency tree, and
+/// exists to bound recursion when rendering untrusted lockfiles.
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// Options for rendering a dependency [`Tree`]
+#[derive(Copy, Clone, Debug)]
+pub struct RenderOptions {
+    /// Symbols to draw the tree with
+    pub symbols: Symbols,
+
+    /// Maximum depth to descend to. Branches nested deeper than this are
+    /// truncated and a notice is printed in their place.
+    pub max_depth: usize,
+
+    /// Maximum width of package labels (in characters). Longer labels are
+    /// truncated with an ellipsis, e.g. to keep the tree readable on narrow
+    /// terminals. Labels aren't truncated if `None`.
+    pub max_width: Option<usize>,
+
+    /// Packages are only expanded the first time they're encountered in a
+    /// tree. Mark later occurrences which would otherwise have been expanded
+    /// with `(*)`, like `cargo tree` does, rather than silently omitting
+    /// their dependencies.
+    pub mark_repeated: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        Self {
+            symbols: Symbols::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_width: None,
+            mark_repeated: false,
+        }
+    }
+}
+
 /// Symbols to use when printing the dependency tree
+#[derive(Copy, Clone, Debug)]
 pub struct Symbols {
     down: &'static str,
     tee: &'static str,
     ell: &'static str,
     right: &'static str,
+    ellipsis: &'static str,
 }
 
-impl Default for Symbols {
-    fn default() -> Symbols {
+impl Symbols {
+    /// Unicode box-drawing symbols (the default)
+    pub fn unicode() -> Self {
         Self {
             down: "│",
             tee: "├",
             ell: "└",
             right: "─",
+            ellipsis: "…",
+        }
+    }
+
+    /// ASCII-only symbols, for terminals and log viewers which can't
+    /// display box-drawing characters
+    pub fn ascii() -> Self {
+        Self {
+            down: "|",
+            tee: "+",
+            ell: "`",
+            right: "-",
+            ellipsis: "...",
         }
     }
 }
 
+impl Default for Symbols {
+    fn default() -> Symbols {
+        Self::unicode()
+    }
+}
+
+/// Format the label of a package in a dependency tree the default way, i.e.
+/// as its name and version
+pub fn default_label(package: &Package) -> String {
+    format!("{} {}", &package.name, &package.version)
+}
+
+/// Write a quoted DOT string, escaping quotes and backslashes
+fn write_dot_string(w: &mut impl io::Write, s: &str) -> io::Result<()> {
+    write!(w, "\"")?;
+
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => write!(w, "\\{}", c)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+
+    write!(w, "\"")
+}
+
 /// Dependency tree presenter
-struct Presenter<'g, 's> {
+struct Presenter<'g, 'o> {
     /// Dependency graph being displayed
     graph: &'g Graph,
 
-    /// Symbols to use to display graph
-    symbols: &'s Symbols,
+    /// Options to use to display graph
+    options: &'o RenderOptions,
+
+    /// Custom function to format package labels with (if any)
+    label: Option<&'o dyn Fn(&Package) -> String>,
 
     /// Are there continuing levels?
     levels_continue: Vec<bool>,
@@ -116,12 +405,13 @@ struct Presenter<'g, 's> {
     visited: Set<NodeIndex>,
 }
 
-impl<'g, 's> Presenter<'g, 's> {
+impl<'g, 'o> Presenter<'g, 'o> {
     /// Create a new dependency tree `Presenter`.
-    fn new(graph: &'g Graph, symbols: &'s Symbols) -> Self {
+    fn new(graph: &'g Graph, options: &'o RenderOptions) -> Self {
         Self {
             graph,
-            symbols,
+            options,
+            label: None,
             levels_continue: vec![],
             visited: Set::new(),
         }
@@ -135,35 +425,66 @@ impl<'g, 's> Presenter<'g, 's> {
         direction: EdgeDirection,
     ) -> io::Result<()> {
         let package = &self.graph[node_index];
-        let new = self.visited.insert(node_index);
 
-        if let Some((&last_continues, rest)) = self.levels_continue.split_last() {
-            for &continues in rest {
-                let c = if continues { self.symbols.down } else { " " };
-                write!(w, "{}   ", c)?;
-            }
+        // Nodes are only marked as visited once their dependencies have been
+        // printed, so ones cut off by `max_depth` are expanded again later
+        let new = !self.visited.contains(&node_index);
 
-            let c = if last_continues {
-                self.symbols.tee
-            } else {
-                self.symbols.ell
-            };
+        use petgraph::visit::EdgeRef;
+        let dependencies = self
+            .graph
+            .edges_directed(node_index, direction)
+            .map(|edge| match direction {
+                EdgeDirection::Incoming => edge.source(),
+                EdgeDirection::Outgoing => edge.target(),
+            })
+            .collect::<Vec<_>>();
+
+        self.print_prefix(w)?;
+
+        // Only build default labels up front when they might need to be
+        // truncated, so rendering large trees writes straight through to `w`
+        match (self.label, self.options.max_width) {
+            (None, None) => write!(w, "{} {}", &package.name, &package.version)?,
+            (label, max_width) => {
+                let mut label = label.map_or_else(|| default_label(package), |f| f(package));
+
+                if let Some(max_width) = max_width {
+                    if label.chars().count() > max_width {
+                        let ellipsis = self.options.symbols.ellipsis;
+                        let width = max_width.saturating_sub(ellipsis.chars().count());
+                        label = label.chars().take(width).collect();
+                        label.push_str(ellipsis);
+                    }
+                }
 
-            write!(w, "{0}{1}{1} ", c, self.symbols.right)?;
+                write!(w, "{}", label)?;
+            }
+        }
+
+        if !new && self.options.mark_repeated && !dependencies.is_empty() {
+            write!(w, " (*)")?;
         }
 
-        writeln!(w, "{} {}", &package.name, &package.version)?;
+        writeln!(w)?;
 
-        if !new {
+        if !new || dependencies.is_empty() {
             return Ok(());
         }
 
-        use petgraph::visit::EdgeRef;
-        let dependencies = self
-            .graph
-            .edges_directed(node_index, direction)
-            .map(|edge| edge.source())
-            .collect::<Vec<_>>();
+        if self.levels_continue.len() >= self.options.max_depth {
+            self.levels_continue.push(false);
+            self.print_prefix(w)?;
+            writeln!(
+                w,
+                "... (truncated: maximum depth of {} reached)",
+                self.options.max_depth
+            )?;
+            self.levels_continue.pop();
+            return Ok(());
+        }
+
+        self.visited.insert(node_index);
 
         for (i, dependency) in dependencies.iter().enumerate() {
             self.levels_continue.push(i < (dependencies.len() - 1));
@@ -173,6 +494,28 @@ impl<'g, 's> Presenter<'g, 's> {
 
         Ok(())
     }
+
+    /// Print the tree drawing which precedes a node at the current level.
+    fn print_prefix(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let symbols = &self.options.symbols;
+
+        if let Some((&last_continues, rest)) = self.levels_continue.split_last() {
+            for &continues in rest {
+                let c = if continues { symbols.down } else { " " };
+                write!(w, "{}   ", c)?;
+            }
+
+            let c = if last_continues {
+                symbols.tee
+            } else {
+                symbols.ell
+            };
+
+            write!(w, "{0}{1}{1} ", c, symbols.right)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -184,9 +527,342 @@ mod tests {
         Lockfile::load("Cargo.lock").unwrap()
     }
 
+    /// Generate a V2 lockfile which is a single chain of `len` packages,
+    /// where the last package depends on the first (i.e. a cycle)
+    fn cyclic_chain_lockfile(len: usize) -> Lockfile {
+        let mut toml = String::new();
+
+        for i in 0..len {
+            toml.push_str(&format!(
+                "[[package]]\nname = \"pkg{}\"\nversion = \"0.1.0\"\ndependencies = [\"pkg{}\"]\n\n",
+                i,
+                (i + 1) % len
+            ));
+        }
+
+        toml.parse().unwrap()
+    }
+
+    /// Render the tree of dependents of the first package in the lockfile
+    fn render_first(lockfile: &Lockfile, options: &RenderOptions) -> String {
+        let tree = Tree::new(lockfile).unwrap();
+        let index = tree.nodes()[&Dependency::from(&lockfile.packages[0])];
+        let mut output = Vec::new();
+
+        tree.render_with_options(&mut output, index, EdgeDirection::Incoming, options)
+            .unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn find_path() {
+        let lockfile = cyclic_chain_lockfile(4);
+        let tree = Tree::new(&lockfile).unwrap();
+        let index = |i: usize| tree.nodes()[&Dependency::from(&lockfile.packages[i])];
+
+        assert_eq!(
+            tree.find_path(index(2), index(1)),
+            Some(vec![index(2), index(3), index(0), index(1)])
+        );
+        assert_eq!(tree.find_path(index(1), index(1)), Some(vec![index(1)]));
+
+        let lockfile = load_lockfile();
+        let tree = Tree::new(&lockfile).unwrap();
+        let find = |name: &str| {
+            let package = lockfile
+                .packages
+                .iter()
+                .find(|pkg| pkg.name.as_str() == name)
+                .unwrap();
+            tree.nodes()[&Dependency::from(package)]
+        };
+
+        assert_eq!(tree.find_path(find("serde"), find("cargo-lock")), None);
+    }
+
     #[test]
     fn compute_tree() {
         // TODO(tarcieri): test dependency tree is computed correctly
         Tree::new(&load_lockfile()).unwrap();
     }
+
+    #[test]
+    fn paths_to_package() {
+        let lockfile = load_lockfile();
+        let tree = Tree::new(&lockfile).unwrap();
+
+        let find = |name: &str| {
+            let package = lockfile
+                .packages
+                .iter()
+                .find(|pkg| pkg.name.as_str() == name)
+                .unwrap();
+
+            tree.nodes()[&Dependency::from(package)]
+        };
+
+        let paths = tree.paths_to(find("semver-parser"), None);
+        assert_eq!(
+            paths,
+            vec![vec![
+                find("cargo-lock"),
+                find("semver"),
+                find("semver-parser")
+            ]]
+        );
+
+        let paths = tree.paths_to(find("proc-macro2"), None);
+        assert!(paths.len() > 1);
+        assert!(paths.iter().all(|path| path[0] == find("cargo-lock")));
+        assert_eq!(tree.paths_to(find("proc-macro2"), Some(1)).len(), 1);
+    }
+
+    #[test]
+    fn paths_to_package_in_cycle() {
+        // No package is a root, since every package depends on another
+        let lockfile = cyclic_chain_lockfile(4);
+        let tree = Tree::new(&lockfile).unwrap();
+        let index = tree.nodes()[&Dependency::from(&lockfile.packages[0])];
+        assert!(tree.paths_to(index, None).is_empty());
+    }
+
+    #[test]
+    fn paths_to_package_in_long_chain() {
+        // Break the cycle so the first package is the only root
+        let mut lockfile = cyclic_chain_lockfile(DEFAULT_MAX_DEPTH * 4);
+        lockfile.packages.last_mut().unwrap().dependencies.clear();
+
+        let tree = Tree::new(&lockfile).unwrap();
+        let index = tree.nodes()[&Dependency::from(lockfile.packages.last().unwrap())];
+        let paths = tree.paths_to(index, None);
+
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), lockfile.packages.len());
+        assert_eq!(
+            paths[0][0],
+            tree.nodes()[&Dependency::from(&lockfile.packages[0])]
+        );
+    }
+
+    #[test]
+    fn render_with_custom_labels() {
+        let lockfile = cyclic_chain_lockfile(3);
+        let tree = Tree::new(&lockfile).unwrap();
+        let index = tree.nodes()[&Dependency::from(&lockfile.packages[0])];
+
+        let mut output = Vec::new();
+        tree.render_with(&mut output, index, EdgeDirection::Incoming, |pkg| {
+            pkg.name.as_str().to_uppercase()
+        })
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "PKG0\n└── PKG2\n    └── PKG1\n        └── PKG0\n"
+        );
+
+        let mut output = Vec::new();
+        tree.render_with(&mut output, index, EdgeDirection::Incoming, default_label)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            render_first(&lockfile, &RenderOptions::default())
+        );
+    }
+
+    #[test]
+    fn render_dot() {
+        let lockfile = "[[package]]\nname = \"my-app\"\nversion = \"0.1.0\"\ndependencies = [\"serde-json\"]\n\n\
+            [[package]]\nname = \"serde-json\"\nversion = \"1.0.0\"\n"
+            .parse::<Lockfile>()
+            .unwrap();
+
+        let mut output = Vec::new();
+        Tree::new(&lockfile)
+            .unwrap()
+            .render_dot(&mut output)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "digraph dependencies {\n    \
+             n0 [label=\"my-app 0.1.0\"];\n    \
+             n1 [label=\"serde-json 1.0.0\"];\n    \
+             n0 -> n1;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn subgraph() {
+        let lockfile = "[[package]]\nname = \"a\"\nversion = \"0.1.0\"\ndependencies = [\"b\"]\n\n\
+            [[package]]\nname = \"b\"\nversion = \"0.1.0\"\ndependencies = [\"c\"]\n\n\
+            [[package]]\nname = \"c\"\nversion = \"0.1.0\"\n\n\
+            [[package]]\nname = \"d\"\nversion = \"0.1.0\"\ndependencies = [\"c\"]\n"
+            .parse::<Lockfile>()
+            .unwrap();
+
+        let tree = Tree::new(&lockfile).unwrap();
+        let b = tree.nodes()[&Dependency::from(&lockfile.packages[1])];
+
+        let names = |subgraph: &Tree| {
+            subgraph
+                .graph()
+                .node_indices()
+                .map(|index| subgraph.graph()[index].name.as_str().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let outgoing = tree.subgraph(b, EdgeDirection::Outgoing);
+        assert_eq!(names(&outgoing), ["b", "c"]);
+        assert_eq!(outgoing.graph().edge_count(), 1);
+
+        let incoming = tree.subgraph(b, EdgeDirection::Incoming);
+        assert_eq!(names(&incoming), ["a", "b"]);
+
+        let mut output = Vec::new();
+        outgoing.render_dot(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "digraph dependencies {\n    \
+             n0 [label=\"b 0.1.0\"];\n    \
+             n1 [label=\"c 0.1.0\"];\n    \
+             n0 -> n1;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn render_outgoing_tree() {
+        let lockfile = cyclic_chain_lockfile(3);
+        let tree = Tree::new(&lockfile).unwrap();
+        let index = tree.nodes()[&Dependency::from(&lockfile.packages[0])];
+
+        let mut output = Vec::new();
+        tree.render(&mut output, index, EdgeDirection::Outgoing)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "pkg0 0.1.0\n└── pkg1 0.1.0\n    └── pkg2 0.1.0\n        └── pkg0 0.1.0\n"
+        );
+    }
+
+    #[test]
+    fn render_cyclic_tree() {
+        let output = render_first(&cyclic_chain_lockfile(8), &RenderOptions::default());
+
+        // Every package is printed once, plus the revisited first package
+        assert_eq!(output.lines().count(), 9);
+        assert!(!output.contains("truncated"));
+    }
+
+    #[test]
+    fn render_truncated_at_max_depth() {
+        let options = RenderOptions {
+            max_depth: 3,
+            ..RenderOptions::default()
+        };
+
+        let output = render_first(&cyclic_chain_lockfile(8), &options);
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[4],
+            "            └── ... (truncated: maximum depth of 3 reached)"
+        );
+    }
+
+    #[test]
+    fn render_marked_repeats() {
+        let options = RenderOptions {
+            mark_repeated: true,
+            ..RenderOptions::default()
+        };
+
+        let output = render_first(&cyclic_chain_lockfile(2), &options);
+        assert_eq!(
+            output,
+            "pkg0 0.1.0\n└── pkg1 0.1.0\n    └── pkg0 0.1.0 (*)\n"
+        );
+
+        let output = render_first(&cyclic_chain_lockfile(2), &RenderOptions::default());
+        assert_eq!(output, "pkg0 0.1.0\n└── pkg1 0.1.0\n    └── pkg0 0.1.0\n");
+    }
+
+    #[test]
+    fn render_ascii() {
+        let options = RenderOptions {
+            symbols: Symbols::ascii(),
+            max_width: Some(7),
+            ..RenderOptions::default()
+        };
+
+        let output = render_first(&cyclic_chain_lockfile(2), &options);
+        assert_eq!(output, "pkg0...\n`-- pkg1...\n    `-- pkg0...\n");
+        assert!(output.is_ascii());
+    }
+
+    #[test]
+    fn render_truncated_labels() {
+        let options = RenderOptions {
+            max_width: Some(6),
+            ..RenderOptions::default()
+        };
+
+        let output = render_first(&cyclic_chain_lockfile(2), &options);
+        assert_eq!(output, "pkg0 …\n└── pkg1 …\n    └── pkg0 …\n");
+
+        let options = RenderOptions {
+            max_width: Some(10),
+            ..RenderOptions::default()
+        };
+
+        let output = render_first(&cyclic_chain_lockfile(2), &options);
+        assert!(output.starts_with("pkg0 0.1.0\n"));
+    }
+
+    #[test]
+    fn render_diamond_with_max_depth() {
+        let lockfile = "[[package]]\nname = \"a\"\nversion = \"0.1.0\"\ndependencies = [\"d\"]\n\n\
+                        [[package]]\nname = \"d\"\nversion = \"0.1.0\"\n\n\
+                        [[package]]\nname = \"m\"\nversion = \"0.1.0\"\ndependencies = [\"a\"]\n\n\
+                        [[package]]\nname = \"root\"\nversion = \"0.1.0\"\ndependencies = [\"a\", \"m\"]\n"
+            .parse::<Lockfile>()
+            .unwrap();
+
+        let tree = Tree::new(&lockfile).unwrap();
+        let root = tree.nodes()[&Dependency::from(&lockfile.packages[3])];
+
+        for &mark_repeated in &[false, true] {
+            let options = RenderOptions {
+                max_depth: 2,
+                mark_repeated,
+                ..RenderOptions::default()
+            };
+
+            let mut output = Vec::new();
+            tree.render_with_options(&mut output, root, EdgeDirection::Outgoing, &options)
+                .unwrap();
+            let output = String::from_utf8(output).unwrap();
+
+            // `a` is cut off below `m`, so it's expanded where it's shallower
+            assert!(output.contains("d 0.1.0"), "{}", output);
+            assert!(!output.contains("(*)"), "{}", output);
+        }
+    }
+
+    #[test]
+    fn render_deep_tree_with_default_max_depth() {
+        let output = render_first(
+            &cyclic_chain_lockfile(DEFAULT_MAX_DEPTH * 4),
+            &RenderOptions::default(),
+        );
+
+        assert_eq!(output.lines().count(), DEFAULT_MAX_DEPTH + 2);
+        assert!(output.ends_with("maximum depth of 512 reached)\n"));
+    }
 }
@@ -20,12 +20,15 @@ macro_rules! format_err {
 }
 
 /// Create and return an error with a formatted message
+///
+/// The expansion has no trailing semicolon so `fail!` can be used in
+/// expression position, e.g. as a `match` arm.
 macro_rules! fail {
     ($kind:path, $msg:expr) => {
-        return Err(format_err!($kind, $msg).into());
+        return Err(format_err!($kind, $msg).into())
     };
     ($kind:path, $fmt:expr, $($arg:tt)+) => {
-        fail!($kind, &format!($fmt, $($arg)+));
+        fail!($kind, &format!($fmt, $($arg)+))
     };
 }
 
@@ -40,6 +43,35 @@ pub enum ErrorKind {
 
     /// Errors related to versions
     Version,
+
+    /// The same package (i.e. name, version, and source) is defined more
+    /// than once, or conflicting definitions of it were found
+    DuplicatePackage,
+
+    /// A dependency refers to a package which isn't in the lockfile
+    DanglingDependency,
+
+    /// Unsupported `Cargo.lock` format version
+    UnsupportedVersion,
+
+    /// A `[patch]` doesn't apply to any package in the lockfile
+    UnusedPatch,
+
+    /// The lockfile has no contents at all besides comments, i.e. neither
+    /// `[[package]]` entries nor a `version` field (e.g. because it was
+    /// truncated)
+    MissingPackages,
+
+    /// The input is some other kind of TOML file, i.e. a `Cargo.toml`
+    /// manifest rather than a `Cargo.lock` file
+    NotALockfile,
+
+    /// A package which was asked about isn't in the lockfile
+    PackageNotFound,
+
+    /// The root packages of the dependency graph couldn't be identified, e.g.
+    /// because every package is part of a dependency cycle
+    MissingRoots,
 }
 
 impl Display for ErrorKind {
@@ -48,6 +80,14 @@ impl Display for ErrorKind {
             ErrorKind::Io => "I/O operation failed",
             ErrorKind::Parse => "parse error",
             ErrorKind::Version => "bad version",
+            ErrorKind::DuplicatePackage => "duplicate package",
+            ErrorKind::DanglingDependency => "dangling dependency",
+            ErrorKind::UnsupportedVersion => "unsupported version",
+            ErrorKind::UnusedPatch => "unused patch",
+            ErrorKind::MissingPackages => "missing packages",
+            ErrorKind::NotALockfile => "not a lockfile",
+            ErrorKind::PackageNotFound => "package not found",
+            ErrorKind::MissingRoots => "missing root packages",
         };
 
         write!(f, "{}", msg)
@@ -80,7 +120,8 @@ impl From<std::num::ParseIntError> for Error {
 
 impl From<toml::de::Error> for Error {
     fn from(other: toml::de::Error) -> Self {
-        format_err!(ErrorKind::Parse, &other)
+        let line_col = other.line_col().map(|(line, col)| (line + 1, col + 1));
+        format_err!(ErrorKind::Parse, &other).with_line_col(line_col)
     }
 }
 
@@ -92,6 +133,10 @@ pub struct Error {
 
     /// Message providing additional information
     msg: String,
+
+    /// Location (line and column) in the input the error occurred at, if
+    /// known
+    line_col: Option<(usize, usize)>,
 }
 
 impl Error {
@@ -100,9 +145,16 @@ impl Error {
         Self {
             kind,
             msg: msg.to_string(),
+            line_col: None,
         }
     }
 
+    /// Set the location (line and column) in the input the error occurred at
+    pub(crate) fn with_line_col(mut self, line_col: Option<(usize, usize)>) -> Self {
+        self.line_col = line_col;
+        self
+    }
+
     /// Obtain the inner `ErrorKind` for this error
     pub fn kind(&self) -> ErrorKind {
         self.kind
@@ -112,6 +164,12 @@ impl Error {
     pub fn msg(&self) -> &str {
         &self.msg
     }
+
+    /// Obtain the line and column (both starting at 1) in the input at which
+    /// a parse error occurred, if known. The message includes them as well.
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        self.line_col
+    }
 }
 
 impl Display for Error {
@@ -0,0 +1,267 @@
+//! Hash functions for fingerprinting lockfiles (see [`Lockfile::content_hash`])
+//!
+//! [`Lockfile::content_hash`]: crate::Lockfile::content_hash
+
+/// Hash functions which can be used to compute content hashes via
+/// [`Lockfile::content_hash_with`], e.g. to produce hashes compatible with
+/// existing cache infrastructure.
+///
+/// [`Lockfile::content_hash_with`]: crate::Lockfile::content_hash_with
+pub trait ContentHasher: Default {
+    /// Feed the given data into the hash function
+    fn update(&mut self, data: &[u8]);
+
+    /// Finish computing the hash, returning the digest
+    fn finalize(self) -> Vec<u8>;
+}
+
+/// SHA-256 round constants
+const K: [u32; 64] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// SHA-256 initial hash value
+const H0: [u32; 8] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+/// The SHA-256 hash function (the default for content hashes, and the
+/// algorithm Cargo uses for package checksums)
+#[derive(Clone, Debug)]
+pub struct Sha256 {
+    /// Intermediate hash value
+    state: [u32; 8],
+
+    /// Partial block which hasn't been processed yet
+    buffer: [u8; 64],
+
+    /// Number of bytes in `buffer`
+    buffered: usize,
+
+    /// Total number of bytes hashed
+    length: u64,
+}
+
+impl Sha256 {
+    /// Compute the SHA-256 digest of the given data
+    pub fn digest(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Self::default();
+        hasher.update(data);
+        hasher.finalize_bytes()
+    }
+
+    /// Finish computing the hash, returning the digest as an array
+    pub fn finalize_bytes(mut self) -> [u8; 32] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        self.update(&[0x80]);
+
+        while self.buffered != 56 {
+            self.update(&[0]);
+        }
+
+        self.update(&bit_length.to_be_bytes());
+        debug_assert_eq!(self.buffered, 0);
+
+        let mut digest = [0u8; 32];
+
+        for (chunk, word) in digest.chunks_mut(4).zip(&self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+
+    /// Process a single 64-byte block
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+
+        for (i, chunk) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (word, value) in self.state.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self {
+            state: H0,
+            buffer: [0; 64],
+            buffered: 0,
+            length: 0,
+        }
+    }
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        while !data.is_empty() {
+            let n = (64 - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+
+            if self.buffered == 64 {
+                let block = self.buffer;
+                self.compress(&block);
+                self.buffered = 0;
+            }
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.finalize_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContentHasher, Sha256};
+
+    /// Format a digest as lowercase hex
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha256_test_vectors() {
+        assert_eq!(
+            hex(&Sha256::digest(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&Sha256::digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&Sha256::digest(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn sha256_incremental() {
+        let data = vec![b'a'; 1_000_000];
+        let mut hasher = Sha256::default();
+
+        for chunk in data.chunks(997) {
+            hasher.update(chunk);
+        }
+
+        assert_eq!(
+            hex(&hasher.finalize()),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
@@ -20,7 +20,13 @@
 //! $ cargo install cargo-lock
 //! ```
 //!
-//! It supports the following subcommands:
+//! It supports the following subcommands. Those reading a lockfile given via
+//! `-f` read it from STDIN with `-f -`, e.g. `cat Cargo.lock | cargo lock list -f -`.
+//! Without `-f`, the lockfile named by the `CARGO_LOCK` environment variable
+//! is used if set, and otherwise the `Cargo.lock` in the current directory or
+//! the closest of its parent directories which has one (i.e. the workspace
+//! root when run from within a workspace). Given a directory, the
+//! `Cargo.lock` within it is used.
 //!
 //! ### `list`: summarize packages in `Cargo.lock`
 //!
@@ -40,20 +46,208 @@
 //! [...]
 //! ```
 //!
-//! ### `translate`: convert `Cargo.lock` files between the V1 and V2 formats
+//! `Cargo.lock` doesn't record which packages are only dev or build
+//! dependencies. When given the root package's `Cargo.toml` (via `-m`,
+//! default `./Cargo.toml`), the `--no-dev` and `--no-build` flags exclude
+//! packages which are only present because of the root package's
+//! `[dev-dependencies]` or `[build-dependencies]` respectively:
 //!
-//! The `cargo lock translate` subcommand can translate V1 Cargo.lock files to
-//! the [new V2 format] and vice versa:
+//! ```text
+//! $ cargo lock list --no-dev --no-build -m Cargo.toml
+//! ```
+//!
+//! The `--git`, `--path`, and `--registry` flags only list packages from
+//! git repositories, local paths (including workspace members), or
+//! registries respectively, e.g. `cargo lock list --git --path` lists all
+//! packages which aren't from a registry. Without any of them, all packages
+//! are listed.
+//!
+//! The `--paths` flag shows where path dependencies are located, relative to
+//! the current directory and as an absolute path. They don't have a source in
+//! `Cargo.lock`, so the locations of the root package and its direct path
+//! dependencies are read from the root package's `Cargo.toml`.
+//!
+//! With `--json`, the packages are printed as a JSON array of objects with
+//! `name`, `version`, `source`, and `checksum` fields (`null` if absent), as
+//! well as the absolute `path` of path dependencies with `--paths`.
+//!
+//! If any crates are present in more than one version, a warning naming them
+//! is printed to STDERR after the list (see the `dedupe` subcommand for
+//! details), which `--no-warn-duplicates` suppresses:
 //!
 //! ```text
-//! $ cargo lock translate
+//! *** warning: 6 crates resolved to multiple versions (env_logger, hex, proc-macro2, quote, syn, ...)
+//! ```
+//!
+//! ### `translate`: convert `Cargo.lock` files between resolve versions
+//!
+//! The `cargo lock translate` subcommand can translate Cargo.lock files
+//! between resolve versions, e.g. from V1 to the [new V2 format] and back:
+//!
+//! ```text
+//! $ cargo lock translate -v 2
 //! ```
 //!
 //! ...will translate Cargo.lock to the V2 format. To translate a V2 Cargo.lock
 //! file back to the V1 format, use:
 //!
 //! ```text
-//! $ cargo lock translate --v1
+//! $ cargo lock translate -v 1
+//! ```
+//!
+//! Any resolve version (1 through 4) can be selected with `-v`. Without it,
+//! the lockfile keeps its own version and is only reformatted.
+//!
+//! Translating to an older version which can't represent everything in the
+//! lockfile (e.g. git dependencies on an explicit `master` branch prior to
+//! V3, which become dependencies on the default branch, or git references
+//! which V4 percent-encodes) fails with a warning describing what would be
+//! lost, unless `--force` is given.
+//!
+//! The output is formatted exactly like Cargo would. With `--compact`, all
+//! blank lines are omitted instead, e.g. for embedding the lockfile
+//! elsewhere (which Cargo still parses the same, but rewrites when updating
+//! it).
+//!
+//! With `--check`, nothing is written. Instead, like `rustfmt --check`, it
+//! exits with an error (printing a diff) unless the lockfile is already
+//! formatted exactly like its canonical serialization (in its own resolve
+//! version, unless one is given with `-v`), e.g. to check committed
+//! lockfiles in CI:
+//!
+//! ```text
+//! $ cargo lock translate --check
+//! ```
+//!
+//! ### `check`: check a `Cargo.lock` file for problems
+//!
+//! The `cargo lock check` subcommand reports problems with a lockfile and
+//! exits with an error if any are found, e.g. a lockfile whose declared
+//! resolve version (i.e. `version = 3`) disagrees with its contents:
+//!
+//! ```text
+//! $ cargo lock check
+//! *** error: lockfile declares resolve version 3 but its contents indicate version 1
+//! ```
+//!
+//! Dependencies on packages which are missing from the lockfile are also
+//! reported, as are packages present from both the git and sparse crates.io
+//! index (e.g. after partially merging lockfiles), and git dependencies which
+//! aren't pinned to a precise commit when `--require-git-pins` is given.
+//!
+//! Given a `Cargo.toml` via `-m`, the lockfile is also checked against it
+//! like `cargo build --locked` would, without invoking Cargo: every
+//! dependency requirement in its `[dependencies]`, `[dev-dependencies]`, and
+//! `[build-dependencies]` (including platform-specific ones) must be
+//! satisfied by a locked package version. Renamed dependencies are matched
+//! by the package they refer to, while dependencies inherited from the
+//! workspace can't be checked and are skipped with a warning:
+//!
+//! ```text
+//! $ cargo lock check -m Cargo.toml
+//! *** error: no locked version of serde satisfies [dependencies] serde = "^1.0.200"
+//! ```
+//!
+//! With `--fix`, problems which can be repaired automatically (i.e. packages
+//! which aren't sorted, empty sections, a missing `@generated` header,
+//! V1-style checksums in a newer lockfile, and packages present from both
+//! crates.io index protocols) are fixed by rewriting the lockfile
+//! in Cargo's canonical format, reporting what was changed. Use `--check` to
+//! preview these changes without writing anything. Problems which can't be
+//! fixed automatically are still reported as errors.
+//!
+//! ### `diff`: compare two `Cargo.lock` files
+//!
+//! The `cargo lock diff` subcommand prints the packages which were added,
+//! removed, or changed version between an old lockfile (`--old`) and a new
+//! one (`--new`, by default the discovered `Cargo.lock`), and exits with an error if there
+//! are any differences, e.g. for use in CI:
+//!
+//! ```text
+//! $ cargo lock diff --old Cargo.lock.orig
+//! version changed:
+//! ~ serde 1.0.104 -> 1.0.105
+//!
+//! source changed:
+//! ! url 2.1.1: registry+https://github.com/rust-lang/crates.io-index -> git+https://github.com/example/url#0123456789abcdef0123456789abcdef01234567
+//! ```
+//!
+//! Packages whose version stayed the same but whose source changed (e.g. a
+//! crate redirected to a git fork) are reported separately, as they're easy
+//! to miss. Use `--normalize-sources` to ignore changes between the git and
+//! sparse forms of the crates.io index. When the `http` feature is enabled,
+//! `--old` may also be an `http(s)` URL, which is downloaded using the `curl`
+//! command (unless `--offline` is given or `CARGO_NET_OFFLINE=true` is set).
+//!
+//! ### `dedupe`: report crates present in more than one version
+//!
+//! The `cargo lock dedupe` subcommand lists every crate present in more than
+//! one version (along with the source of each), and exits with an error if
+//! there are any, e.g. to guard against accidental duplicates in CI. Versions
+//! which are semver-compatible, and so could likely be unified by updating
+//! the dependencies requiring the older ones, are suggested as well:
+//!
+//! ```text
+//! $ cargo lock dedupe
+//! syn:
+//!   - 1.0.5 (registry+https://github.com/rust-lang/crates.io-index)
+//!   - 1.0.14 (registry+https://github.com/rust-lang/crates.io-index)
+//!   could be unified: 1.0.5, 1.0.14 -> 1.0.14
+//! ```
+//!
+//! With `--check`, only the duplicates are reported.
+//!
+//! ### `stats`: summarize the contents of `Cargo.lock`
+//!
+//! The `cargo lock stats` subcommand prints aggregate counts about the
+//! packages in the lockfile, e.g. for dashboards. Packages without a source
+//! (i.e. workspace members) count as path packages, and "other" sources are
+//! alternate registries as well as local registry and directory sources:
+//!
+//! ```text
+//! $ cargo lock stats
+//! packages: 23
+//! unique names: 23
+//! duplicated crates: 0
+//! sources:
+//!   crates.io: 22
+//!   git: 0
+//!   path: 1
+//!   other: 0
+//! ```
+//!
+//! With `--json`, the same counts are printed as a JSON object with
+//! `packages`, `names`, `duplicates`, and `sources` fields.
+//!
+//! ### `version`: print the resolve version of `Cargo.lock`
+//!
+//! The `cargo lock version` subcommand prints the resolve version (i.e.
+//! format) of a lockfile, reading only as much of it as needed rather than
+//! parsing all of its packages:
+//!
+//! ```text
+//! $ cargo lock version
+//! V2
+//! ```
+//!
+//! ### `sbom`: generate a software bill of materials
+//!
+//! When the `cyclonedx` feature is enabled, the `cargo lock sbom` subcommand
+//! prints a minimal [CycloneDX] SBOM (in JSON) with a component for every
+//! package in the lockfile, identified by a package URL like
+//! `pkg:cargo/serde@1.0.104` and including its checksum if known:
+//!
+//! ```text
+//! $ cargo lock sbom > bom.json
+//! ```
+//!
+//! Packages from other sources have them recorded in package URL qualifiers
+//! (e.g. `repository_url` for other registries). When crates are resolved
+//! from a mirror of crates.io, use `--crates-io-index` to give its index URL
+//! so that packages from it get plain package URLs instead:
+//!
+//! ```text
+//! $ cargo lock sbom --crates-io-index https://mirror.example.com/index/
 //! ```
 //!
 //! ### `tree`: provide information for how a dependency is included
@@ -77,6 +271,99 @@
 //!        └── cargo-lock 3.0.0
 //! ```
 //!
+//! Passing several lockfiles (i.e. `-f a/Cargo.lock -f b/Cargo.lock`) merges
+//! them before drawing a combined tree, e.g. to analyze related workspaces
+//! together. Packages with the same name, version, and source are merged, so
+//! if their checksums differ between the lockfiles, an error is reported.
+//!
+//! On narrow terminals, `--limit-width N` truncates package labels to `N`
+//! columns (with an ellipsis) to keep the structure of the tree readable.
+//! For deeply nested trees, `--depth N` only draws `N` levels below the given
+//! dependency (i.e. `--depth 1` only shows the packages depending on it
+//! directly), marking where the tree was truncated.
+//!
+//! With `--to NAME`, only the shortest dependency path from the given
+//! dependency to the package named `NAME` is printed (or an error if it
+//! doesn't depend on it), e.g. to find out how two packages end up connected:
+//!
+//! ```text
+//! $ cargo lock tree cargo-lock --to unicode-xid
+//! cargo-lock 4.0.1 -> serde 1.0.104 -> serde_derive 1.0.104 -> syn 1.0.14 -> unicode-xid 0.2.0
+//! ```
+//!
+//! By default, trees show the packages which depend on the given dependency.
+//! Use `--direction outgoing` to show what the given dependency pulls in
+//! instead.
+//!
+//! Without any dependency names, trees are drawn for the root packages (i.e.
+//! those nothing else depends on, like the members of a workspace), showing
+//! what they pull in.
+//!
+//! To find out why crates end up in the lockfile more than once, use
+//! `--duplicates` to draw trees for every version of each crate present in
+//! more than one version (see the `dedupe` subcommand), with a section per
+//! crate:
+//!
+//! ```text
+//! $ cargo lock tree --duplicates --depth 1
+//! crossbeam-epoch 0.3.1
+//! └── crossbeam-deque 0.2.0
+//!     └── ... (truncated: maximum depth of 1 reached)
+//! crossbeam-epoch 0.7.2
+//! └── crossbeam-deque 0.6.3
+//!     └── ... (truncated: maximum depth of 1 reached)
+//! ```
+//!
+//! Dependency names are looked up ignoring differences in case and between
+//! hyphens and underscores (e.g. `serde-derive` finds `serde_derive`),
+//! unless a package has exactly the given name. If that's ambiguous, the
+//! names which might have been meant are suggested instead.
+//!
+//! Trees are drawn using Unicode box-drawing characters. For terminals, CI
+//! logs, or files which can't display them, use `--charset ascii` to draw
+//! them using `|`, `+`, `` ` ``, and `-` instead:
+//!
+//! ```text
+//! $ cargo lock tree gumdrop_derive --charset ascii
+//! gumdrop_derive 0.7.0
+//! `-- gumdrop 0.7.0
+//!     `-- cargo-lock 4.0.1
+//! ```
+//!
+//! The dependencies of a package are only drawn the first time it appears
+//! in a tree. Use `--dedupe-subtrees` to show later appearances of packages
+//! with dependencies as `name version (*)`, like `cargo tree` does, making
+//! it clear that they've been omitted.
+//!
+//! With `--dot`, the whole dependency graph is written in the [Graphviz] DOT
+//! language instead, e.g. to render it as an image:
+//!
+//! ```text
+//! $ cargo lock tree --dot | dot -Tsvg > dependencies.svg
+//! ```
+//!
+//! Given a dependency name, only that package's tree (i.e. the packages
+//! depending on it, or its dependencies with `--direction outgoing`) is
+//! exported, keeping diagrams of large workspaces legible:
+//!
+//! ```text
+//! $ cargo lock tree semver --direction outgoing --dot
+//! ```
+//!
+//! ### `why`: explain why a package is included
+//!
+//! The `cargo lock why` subcommand prints every dependency path from a root
+//! package (i.e. one nothing else depends on) to the given package:
+//!
+//! ```text
+//! $ cargo lock why semver-parser
+//! cargo-lock 4.0.1 -> semver 0.9.0 -> semver-parser 0.7.0
+//! ```
+//!
+//! With `--json`, the paths are printed as a JSON array of arrays of package
+//! IDs (i.e. `name version (source)` strings). As the number of paths can be
+//! very large, at most 100 are found unless overridden using `--limit`.
+//!
 //! # Dependency tree API
 //!
 //! When the `dependency-tree` feature of this crate is enabled, it supports
@@ -88,10 +375,27 @@
 //! This same graph representation of a `Cargo.lock` file is programatically
 //! available via this crate's API.
 //!
+//! # SPDX export
+//!
+//! When the `spdx` feature of this crate is enabled, `Lockfile::to_spdx`
+//! generates an [SPDX] 2.3 SBOM in the tag-value format, describing every
+//! package with its version, download location, and checksum. Its creation
+//! time can be given in `SbomOptions::created`, and otherwise comes from the
+//! `SOURCE_DATE_EPOCH` environment variable (if set) or the current time.
+//!
+//! # Verifying checksums
+//!
+//! When the `verify` feature of this crate is enabled,
+//! `Package::verify_checksum` checks whether a `.crate` file (e.g. from a
+//! vendor directory) matches the SHA-256 checksum recorded in the lockfile.
+//!
 //! [RustSec]: https://rustsec.org/
 //! [new V2 format]: https://github.com/rust-lang/cargo/pull/7070
 //! [`petgraph`]: https://github.com/petgraph/petgraph
 //! [`cargo-tree`]: https://github.com/sfackler/cargo-tree
+//! [Graphviz]: https://graphviz.org/
+//! [CycloneDX]: https://cyclonedx.org/
+//! [SPDX]: https://spdx.dev/
 
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/RustSec/logos/master/rustsec-logo-lg.png",
@@ -99,12 +403,18 @@
 )]
 #![forbid(unsafe_code)]
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+// `is_some_and`/`is_none_or` and `#[default]` on enums are newer than our MSRV
+#![allow(clippy::unnecessary_map_or, clippy::derivable_impls)]
+// The locked `serde_derive` 1.0.104 puts its impls inside of named `const` items
+#![allow(non_local_definitions)]
 
 #[macro_use]
 pub mod error;
 
 pub mod dependency;
+pub mod hash;
 pub mod lockfile;
+pub mod manifest;
 pub mod metadata;
 pub mod package;
 pub mod patch;
@@ -112,11 +422,20 @@ pub mod patch;
 pub use self::{
     dependency::Dependency,
     error::{Error, ErrorKind},
-    lockfile::{Lockfile, ResolveVersion},
-    metadata::Metadata,
-    package::{Checksum, Name, Package, SourceId, Version},
+    lockfile::{
+        Changeset, DowngradeError, Lockfile, LockfileBuilder, LockfileDiff, LockfileMetrics,
+        ResolveVersion, SerializeOptions, ValidationError,
+    },
+    manifest::Manifest,
+    metadata::{Metadata, MetadataExt},
+    package::{
+        Checksum, CratesIoProtocol, Name, Package, PackageId, SourceId, SourceKind, Version,
+    },
     patch::Patch,
 };
 
+#[cfg(any(feature = "cyclonedx", feature = "spdx"))]
+pub use self::lockfile::SbomOptions;
+
 /// Use `BTreeMap` for all `Map` types in the crate
 use std::collections::BTreeMap as Map;
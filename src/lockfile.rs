@@ -1,22 +1,59 @@
 //! Parser for `Cargo.lock` files
 
+pub mod builder;
+#[cfg(feature = "cyclonedx")]
+mod cyclonedx;
+pub mod diff;
 pub(crate) mod encoding;
+pub mod metrics;
+#[cfg(any(feature = "cyclonedx", feature = "spdx"))]
+pub mod sbom;
+pub mod serialize;
+#[cfg(feature = "spdx")]
+mod spdx;
+mod stream;
+pub mod validate;
+mod vendor;
 pub mod version;
 
-pub use self::version::ResolveVersion;
+pub use self::{
+    builder::LockfileBuilder,
+    diff::{Changeset, LockfileDiff},
+    metrics::LockfileMetrics,
+    serialize::{Newline, SerializeOptions},
+    validate::ValidationError,
+    version::{DowngradeError, ResolveVersion},
+};
+
+#[cfg(any(feature = "cyclonedx", feature = "spdx"))]
+pub use self::sbom::SbomOptions;
 
 use self::encoding::EncodableLockfile;
 use crate::{
     error::{Error, ErrorKind},
+    hash::{ContentHasher, Sha256},
+    manifest::{self, DependencyKind, Manifest, Unsatisfied},
     metadata::Metadata,
-    package::Package,
+    package::{
+        version::is_compatible, CratesIoProtocol, Name, Package, PackageId, SourceId, SourceKind,
+        Version,
+    },
     patch::Patch,
+    Dependency, Map,
+};
+use semver::VersionReq;
+use std::{
+    collections::{BTreeSet, HashSet},
+    convert::TryInto,
+    fmt, fs, io, iter,
+    path::Path,
+    str::FromStr,
 };
-use std::{fs, path::Path, str::FromStr, string::ToString};
-use toml;
 
 #[cfg(feature = "dependency-tree")]
 use crate::dependency::Tree;
+#[cfg(feature = "dependency-tree")]
+use petgraph::visit::Dfs;
 
 /// Parsed Cargo.lock file containing dependencies
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -35,20 +72,1154 @@ pub struct Lockfile {
 
     /// Patches
     pub patch: Patch,
+
+    /// Comment lines at the top of the lockfile (without any trailing blank
+    /// lines), which are written back out verbatim on serialization.
+    ///
+    /// If `None`, the standard "automatically @generated by Cargo" header
+    /// is written instead. Parsing a lockfile which begins with exactly that
+    /// header also yields `None`.
+    pub preamble: Option<String>,
 }
 
 impl Lockfile {
-    /// Load lock data from a `Cargo.lock` file
+    /// Load lock data from a `Cargo.lock` file, or from the `Cargo.lock` file
+    /// within the given directory (e.g. a workspace root).
+    ///
+    /// It's an [`ErrorKind::NotALockfile`] error if the file is a
+    /// `Cargo.toml` manifest instead. Use [`Lockfile::from_str`] (or
+    /// [`str::parse`]) to parse a lockfile which is already in memory.
     pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
-        match fs::read_to_string(path.as_ref()) {
-            Ok(s) => s.parse(),
-            Err(e) => fail!(
-                ErrorKind::Io,
-                "couldn't open {}: {}",
-                path.as_ref().display(),
-                e
-            ),
+        let mut path = path.as_ref().to_owned();
+
+        if path.is_dir() {
+            path.push("Cargo.lock");
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(s) => s.parse().map_err(|e: Error| {
+                if e.kind() == ErrorKind::NotALockfile {
+                    format_err!(
+                        ErrorKind::NotALockfile,
+                        "{} is a Cargo.toml manifest rather than a Cargo.lock file",
+                        path.display()
+                    )
+                } else {
+                    e
+                }
+            }),
+            Err(e) => fail!(ErrorKind::Io, "couldn't open {}: {}", path.display(), e),
+        }
+    }
+
+    /// Get the sorted [`PackageId`]s of all packages in this lockfile.
+    ///
+    /// This is a compact, deterministic fingerprint of the resolved
+    /// dependency set, e.g. for quick comparisons or cache keys.
+    pub fn package_ids(&self) -> Vec<PackageId> {
+        let mut ids = self
+            .packages
+            .iter()
+            .map(PackageId::from)
+            .collect::<Vec<_>>();
+
+        ids.sort();
+        ids
+    }
+
+    /// Compute the differences between this (old) lockfile and the given
+    /// new one. See [`LockfileDiff`] for more information.
+    pub fn diff(&self, new: &Lockfile) -> LockfileDiff {
+        LockfileDiff::new(self, new)
+    }
+
+    /// Compute the changes from the given previous lockfile to this one,
+    /// classifying each of them as an addition, removal, upgrade, downgrade,
+    /// or source change. See [`Changeset`] for more information.
+    ///
+    /// This is based on the same matching of packages as [`Lockfile::diff`],
+    /// so a package whose version and source both changed is an upgrade or
+    /// downgrade.
+    pub fn changeset(&self, previous: &Lockfile) -> Changeset {
+        previous.diff(self).into()
+    }
+
+    /// Find crates which are present in more than one version, e.g. to spot
+    /// duplicates which could be unified to reduce build times.
+    ///
+    /// Returns the packages of each such crate, sorted by name and then by
+    /// version. Sources are ignored when determining whether a crate has
+    /// multiple versions, i.e. packages with the same name and version from
+    /// different sources don't count as duplicates on their own, but all
+    /// packages of a duplicated crate are returned (sorted by source within
+    /// the same version).
+    pub fn find_duplicates(&self) -> Map<Name, Vec<&Package>> {
+        let mut packages = Map::<_, Vec<_>>::new();
+
+        for package in &self.packages {
+            packages
+                .entry(package.name.clone())
+                .or_default()
+                .push(package);
+        }
+
+        packages.retain(|_, packages| {
+            packages.sort_by(|a, b| (&a.version, &a.source).cmp(&(&b.version, &b.source)));
+            packages
+                .windows(2)
+                .any(|pair| pair[0].version != pair[1].version)
+        });
+
+        packages
+    }
+
+    /// Find the packages with the given name whose version matches the given
+    /// requirement, sorted by version (newest first).
+    ///
+    /// Requirements are matched like Cargo does, so pre-release versions
+    /// only match requirements which explicitly include a pre-release of the
+    /// same `major.minor.patch` version (e.g. `1.0.0-beta.2` matches
+    /// `^1.0.0-beta.1`, but not `^0.9`).
+    pub fn query(&self, name: &Name, req: &VersionReq) -> Vec<&Package> {
+        let mut packages = self
+            .packages
+            .iter()
+            .filter(|package| package.name == *name && req.matches(&package.version))
+            .collect::<Vec<_>>();
+
+        packages.sort_by(|a, b| b.version.cmp(&a.version));
+        packages
+    }
+
+    /// Group the packages in this lockfile by the kind of their source (see
+    /// [`SourceId::kind`]), e.g. to spot any which aren't from crates.io.
+    ///
+    /// Packages without a source (i.e. workspace members) are grouped with
+    /// [`SourceKind::Path`] ones. Packages are in lockfile order within each
+    /// group.
+    pub fn packages_by_source_kind(&self) -> Map<SourceKind, Vec<&Package>> {
+        let mut packages = Map::<_, Vec<_>>::new();
+
+        for package in &self.packages {
+            let kind = package
+                .source
+                .as_ref()
+                .map_or(SourceKind::Path, SourceId::kind);
+
+            packages.entry(kind).or_default().push(package);
+        }
+
+        packages
+    }
+
+    /// Compute metrics about the size and shape of this lockfile's
+    /// dependency graph. See [`LockfileMetrics`] for more information.
+    pub fn metrics(&self) -> LockfileMetrics {
+        LockfileMetrics::new(self)
+    }
+
+    /// Compute a SHA-256 hash of this lockfile's contents as a hex string,
+    /// e.g. for use as a cache key.
+    ///
+    /// The hash is computed over the lockfile's canonical serialization (see
+    /// [`Lockfile::to_writer_pretty`]) with the standard header in place of
+    /// the [`Lockfile::preamble`], so it's independent of formatting,
+    /// comments, and package order.
+    pub fn content_hash(&self) -> String {
+        self.content_hash_with::<Sha256>()
+    }
+
+    /// Compute a hash of this lockfile's contents like
+    /// [`Lockfile::content_hash`], but using the given hash function.
+    pub fn content_hash_with<H: ContentHasher>(&self) -> String {
+        let mut raw_lockfile = EncodableLockfile::from(self);
+        raw_lockfile.preamble = None;
+
+        let mut canonical = Vec::new();
+        raw_lockfile
+            .write_pretty(self.version, &SerializeOptions::default(), &mut canonical)
+            .expect("writing to a Vec can't fail");
+
+        let mut hasher = H::default();
+        hasher.update(&canonical);
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Iterate over all dependency edges in this lockfile, i.e. every
+    /// package along with each of its dependencies.
+    ///
+    /// This is a lighter-weight alternative to computing the full dependency
+    /// tree when the edges only need to be scanned once.
+    pub fn iter_dependencies(&self) -> impl Iterator<Item = (&Package, &Dependency)> {
+        self.packages
+            .iter()
+            .chain(self.root.as_ref())
+            .flat_map(|package| package.dependencies.iter().map(move |dep| (package, dep)))
+    }
+
+    /// Write this lockfile to the given writer, producing exactly the same
+    /// output as `to_string()` (i.e. [`Lockfile::to_writer_pretty`]).
+    ///
+    /// The output is streamed a package at a time, rather than first being
+    /// built up in memory in its entirety.
+    pub fn write_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.to_writer_pretty(w)
+    }
+
+    /// Serialize this lockfile to the given writer using exactly the same
+    /// formatting as Cargo does for the same [`ResolveVersion`].
+    ///
+    /// Packages and their dependencies are written in Cargo's canonical
+    /// order, so lockfiles can be edited and rewritten in place without
+    /// causing spurious changes. Any divergence from Cargo's output is a bug.
+    pub fn to_writer_pretty(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.to_writer_with_options(w, &SerializeOptions::default())
+    }
+
+    /// Serialize this lockfile like [`Lockfile::to_writer_pretty`], but using
+    /// the given newline style and optional byte order mark, e.g. for
+    /// interoperability with Windows toolchains which expect them.
+    pub fn to_writer_with_options(
+        &self,
+        w: &mut impl io::Write,
+        options: &SerializeOptions,
+    ) -> io::Result<()> {
+        EncodableLockfile::from(self).write_pretty(self.version, options, w)
+    }
+
+    /// Serialize this lockfile to a string like [`Lockfile::to_writer_with_options`]
+    /// does, e.g. to get a compact form of it (see [`SerializeOptions::compact`]).
+    ///
+    /// With the default options, this is the same as `to_string()`.
+    pub fn to_string_with_options(&self, options: &SerializeOptions) -> String {
+        let mut out = Vec::new();
+        self.to_writer_with_options(&mut out, options)
+            .expect("writing to a Vec can't fail");
+
+        // Only ever contains UTF-8 strings and line endings
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Check whether the declared [`ResolveVersion`] of this lockfile
+    /// disagrees with what its contents indicate, e.g. a lockfile declaring
+    /// `version = 3` which keeps its checksums in `[metadata]` like V1, or
+    /// one declaring V2 which references an explicit `master` branch (which
+    /// only V3 can represent).
+    ///
+    /// Returns the declared and the detected version if they disagree.
+    pub fn resolve_version_mismatch(&self) -> Option<(ResolveVersion, ResolveVersion)> {
+        let explicit_master = self
+            .packages
+            .iter()
+            .chain(self.root.as_ref())
+            .flat_map(|package| {
+                let dependencies = package.dependencies.iter().chain(&package.replace);
+                package
+                    .source
+                    .iter()
+                    .chain(dependencies.filter_map(|dep| dep.source.as_ref()))
+            })
+            .any(SourceId::is_explicit_master_branch);
+
+        let detected = if self.metadata.keys().any(|key| key.is_checksum()) {
+            ResolveVersion::V1
+        } else if explicit_master {
+            ResolveVersion::V3
+        } else {
+            return None;
+        };
+
+        let mismatch = match detected {
+            ResolveVersion::V1 => self.version != ResolveVersion::V1,
+            _ => self.version < detected,
+        };
+
+        if mismatch {
+            Some((self.version, detected))
+        } else {
+            None
+        }
+    }
+
+    /// Guess which versions of Cargo could have produced this lockfile,
+    /// returning a human-readable range like `>= 1.68`.
+    ///
+    /// This is only a heuristic, based on the release in which the signals
+    /// found in the lockfile became Cargo's default:
+    ///
+    /// - V1: `< 1.41` (V2 became the default for new lockfiles in 1.41)
+    /// - V2: `>= 1.41`
+    /// - V3: `>= 1.53`
+    /// - V4: `>= 1.78` (the first release able to read it; it became the
+    ///   default in 1.83)
+    /// - any source using the sparse protocol: `>= 1.68`
+    ///
+    /// Newer versions of Cargo preserve the resolve version of existing
+    /// lockfiles, and may be configured to use older defaults, so the actual
+    /// version may fall outside of the returned range.
+    pub fn likely_cargo_version_range(&self) -> String {
+        let uses_sparse = self
+            .packages
+            .iter()
+            .filter_map(|package| package.source.as_ref())
+            .any(SourceId::is_sparse);
+
+        let minimum = match self.version {
+            ResolveVersion::V1 => None,
+            ResolveVersion::V2 => Some(41),
+            ResolveVersion::V3 => Some(53),
+            ResolveVersion::V4 => Some(78),
+        };
+
+        let minimum = if uses_sparse {
+            minimum.max(Some(68))
+        } else {
+            minimum
+        };
+
+        match minimum {
+            Some(minor) => format!(">= 1.{}", minor),
+            None => "< 1.41".to_owned(),
+        }
+    }
+
+    /// Rewrite all sources (of packages and their dependencies) referring to
+    /// the crates.io index via the sparse protocol into the canonical git
+    /// form. See [`SourceId::normalize_crates_io`] for more information.
+    ///
+    /// This is an opt-in normalization for comparing lockfiles generated
+    /// before and after the switch to the sparse protocol. Note that the
+    /// resulting lockfile no longer reflects what Cargo would write.
+    ///
+    /// [`SourceId::normalize_crates_io`]: crate::SourceId::normalize_crates_io
+    pub fn normalize_crates_io_sources(&mut self) {
+        self.unify_crates_io_protocol(CratesIoProtocol::Git);
+    }
+
+    /// Get the protocols used to access the crates.io index by the sources
+    /// (of packages and their dependencies) in this lockfile.
+    ///
+    /// More than one protocol indicates a lockfile mixing sources from
+    /// before and after the switch to the sparse protocol (see
+    /// [`Lockfile::unify_crates_io_protocol`]).
+    pub fn crates_io_protocols(&self) -> BTreeSet<CratesIoProtocol> {
+        let mut protocols = BTreeSet::new();
+
+        for package in self.packages.iter().chain(&self.root) {
+            let sources = iter::once(&package.source)
+                .chain(package.dependencies.iter().map(|dep| &dep.source))
+                .chain(package.replace.iter().map(|dep| &dep.source));
+
+            protocols.extend(sources.flatten().filter_map(SourceId::crates_io_protocol));
+        }
+
+        protocols.extend(
+            self.patch
+                .unused
+                .iter()
+                .filter_map(|unused| unused.source.as_ref())
+                .filter_map(SourceId::crates_io_protocol),
+        );
+
+        protocols
+    }
+
+    /// Rewrite all sources (of packages and their dependencies) referring to
+    /// the crates.io index to use the given protocol (see
+    /// [`SourceId::with_crates_io_protocol`]). Sources of other registries,
+    /// git repositories, and paths are left unchanged.
+    ///
+    /// Packages present via both protocols end up listed twice with the same
+    /// source, so use [`Lockfile::collapse_mixed_crates_io_sources`] first
+    /// if there are any.
+    ///
+    /// [`SourceId::with_crates_io_protocol`]: crate::SourceId::with_crates_io_protocol
+    pub fn unify_crates_io_protocol(&mut self, to: CratesIoProtocol) {
+        let unify = |source: &mut Option<SourceId>| {
+            if let Some(source) = source {
+                *source = source.with_crates_io_protocol(to);
+            }
+        };
+
+        for package in self.packages.iter_mut().chain(self.root.as_mut()) {
+            unify(&mut package.source);
+
+            for dependency in package.dependencies.iter_mut().chain(&mut package.replace) {
+                unify(&mut dependency.source);
+            }
+        }
+
+        for unused in &mut self.patch.unused {
+            unify(&mut unused.source);
+        }
+    }
+
+    /// Find packages which are present twice with the same name and version,
+    /// once from the crates.io index accessed via git (`registry+`) and once
+    /// via the sparse protocol (`sparse+`).
+    ///
+    /// These aren't genuinely distinct packages, but rather an artifact of
+    /// (partially) merging lockfiles generated before and after the switch
+    /// to the sparse protocol. Returns `(registry, sparse)` pairs, sorted by
+    /// name and version. See [`Lockfile::collapse_mixed_crates_io_sources`]
+    /// to fix them.
+    pub fn mixed_crates_io_sources(&self) -> Vec<(&Package, &Package)> {
+        let crates_io = |package: &Package, sparse: bool| {
+            package.source.as_ref().map_or(false, |s| {
+                s.is_default_registry() && s.is_sparse() == sparse
+            })
+        };
+
+        let registry_packages = self
+            .packages
+            .iter()
+            .filter(|package| crates_io(package, false))
+            .map(|package| ((&package.name, &package.version), package))
+            .collect::<Map<_, _>>();
+
+        let mut pairs = self
+            .packages
+            .iter()
+            .filter(|package| crates_io(package, true))
+            .filter_map(|sparse| {
+                registry_packages
+                    .get(&(&sparse.name, &sparse.version))
+                    .map(|&registry| (registry, sparse))
+            })
+            .collect::<Vec<_>>();
+
+        pairs.sort_by(|a, b| (&a.0.name, &a.0.version).cmp(&(&b.0.name, &b.0.version)));
+        pairs
+    }
+
+    /// Collapse the package pairs found by
+    /// [`Lockfile::mixed_crates_io_sources`] into a single package using the
+    /// git form of the crates.io index, returning the number of packages
+    /// removed.
+    ///
+    /// References to the removed packages from other packages' dependencies
+    /// are rewritten to point at the remaining package. Its checksum is taken
+    /// from the removed package if it didn't have one.
+    pub fn collapse_mixed_crates_io_sources(&mut self) -> usize {
+        let removed = self
+            .mixed_crates_io_sources()
+            .into_iter()
+            .map(|(_, sparse)| PackageId::from(sparse))
+            .collect::<Vec<_>>();
+
+        for id in &removed {
+            let position = self
+                .packages
+                .iter()
+                .position(|package| PackageId::from(package) == *id)
+                .unwrap();
+
+            let sparse = self.packages.remove(position);
+            let source = sparse.source.as_ref().map(SourceId::normalize_crates_io);
+
+            let registry = self
+                .packages
+                .iter_mut()
+                .find(|pkg| {
+                    pkg.name == id.name && pkg.version == id.version && pkg.source == source
+                })
+                .unwrap();
+
+            if registry.checksum.is_none() {
+                registry.checksum = sparse.checksum;
+            }
+
+            for dependency in sparse.dependencies {
+                if !registry.dependencies.contains(&dependency) {
+                    registry.dependencies.push(dependency);
+                }
+            }
+        }
+
+        for package in self.packages.iter_mut().chain(self.root.as_mut()) {
+            for dependency in package.dependencies.iter_mut().chain(&mut package.replace) {
+                if removed.contains(&PackageId::from(&*dependency)) {
+                    dependency.source = dependency
+                        .source
+                        .as_ref()
+                        .map(SourceId::normalize_crates_io);
+                }
+            }
+        }
+
+        removed.len()
+    }
+
+    /// Merge the packages, metadata, and patches of another lockfile into
+    /// this one, e.g. to analyze the dependencies of related workspaces
+    /// together. Packages are sorted by name, version, and source afterwards,
+    /// and the newer of both lockfiles' [`ResolveVersion`]s is used (as it
+    /// can represent the contents of both).
+    ///
+    /// Packages present in both lockfiles (i.e. with the same name, version,
+    /// and source) are only included once, with the union of their
+    /// dependencies. It's an [`ErrorKind::DuplicatePackage`] error (which
+    /// leaves this lockfile unchanged) if such packages have different
+    /// checksums, or if both lockfiles have different values for the same
+    /// metadata key (e.g. a V1 checksum entry).
+    pub fn merge(&mut self, other: &Lockfile) -> Result<(), Error> {
+        let mut positions = self
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(i, package)| (PackageId::from(package), i))
+            .collect::<Map<_, _>>();
+
+        for package in &other.packages {
+            let existing = match positions.get(&PackageId::from(package)) {
+                Some(&i) => &self.packages[i],
+                None => continue,
+            };
+
+            if let (Some(ours), Some(theirs)) = (&existing.checksum, &package.checksum) {
+                if ours != theirs {
+                    fail!(
+                        ErrorKind::DuplicatePackage,
+                        "conflicting checksums for {}: {} vs {}",
+                        PackageId::from(package),
+                        ours,
+                        theirs
+                    );
+                }
+            }
+        }
+
+        for (key, value) in &other.metadata {
+            if let Some(ours) = self.metadata.get(key) {
+                if ours != value {
+                    fail!(
+                        ErrorKind::DuplicatePackage,
+                        "conflicting values for metadata key `{}`: {} vs {}",
+                        key,
+                        ours,
+                        value
+                    );
+                }
+            }
+        }
+
+        for package in &other.packages {
+            let id = PackageId::from(package);
+
+            match positions.get(&id) {
+                Some(&i) => {
+                    let existing = &mut self.packages[i];
+
+                    if existing.checksum.is_none() {
+                        existing.checksum = package.checksum.clone();
+                    }
+
+                    for dependency in &package.dependencies {
+                        if !existing.dependencies.contains(dependency) {
+                            existing.dependencies.push(dependency.clone());
+                        }
+                    }
+                }
+                None => {
+                    positions.insert(id, self.packages.len());
+                    self.packages.push(package.clone());
+                }
+            }
+        }
+
+        for (key, value) in &other.metadata {
+            self.metadata.insert(key.clone(), value.clone());
+        }
+
+        for unused in &other.patch.unused {
+            if !self.patch.unused.contains(unused) {
+                self.patch.unused.push(unused.clone());
+            }
+        }
+
+        self.packages.sort();
+
+        self.version = self.version.max(other.version);
+        Ok(())
+    }
+
+    /// Add a package to this lockfile, keeping packages sorted by name,
+    /// version, and source.
+    ///
+    /// It's an [`ErrorKind::DuplicatePackage`] error if a package with the
+    /// same name, version, and source is already present. The package's
+    /// dependencies aren't checked: see [`Lockfile::dangling_dependencies`].
+    pub fn add_package(&mut self, package: Package) -> Result<(), Error> {
+        fn key(pkg: &Package) -> (&Name, &Version, &Option<SourceId>) {
+            (&pkg.name, &pkg.version, &pkg.source)
+        }
+
+        let id = PackageId::from(&package);
+
+        if self.packages.iter().any(|pkg| PackageId::from(pkg) == id) {
+            fail!(
+                ErrorKind::DuplicatePackage,
+                "package defined more than once: {}",
+                id
+            );
+        }
+
+        let index = self
+            .packages
+            .iter()
+            .position(|pkg| key(pkg) > key(&package))
+            .unwrap_or(self.packages.len());
+
+        self.packages.insert(index, package);
+        Ok(())
+    }
+
+    /// Sort this lockfile's packages by name, version, and source, and each
+    /// package's dependencies, in the same order Cargo writes them.
+    ///
+    /// This normalizes lockfiles before comparing them, regardless of how
+    /// they were produced. Sorting is idempotent. `[metadata]` is always
+    /// kept sorted by key, so it's unaffected.
+    pub fn sort(&mut self) {
+        self.packages.sort();
+
+        // Cargo sorts dependencies by their string fields
+        for package in &mut self.packages {
+            package.dependencies.sort_by_cached_key(|dep| {
+                (
+                    dep.name.to_string(),
+                    dep.version.to_string(),
+                    dep.source.as_ref().map(ToString::to_string),
+                )
+            });
+        }
+    }
+
+    /// Iterate over this lockfile's packages in the order they're serialized
+    /// in (i.e. sorted by name, version, and source like [`Lockfile::sort`]
+    /// does), regardless of the order of [`Lockfile::packages`].
+    ///
+    /// Packages aren't copied, only the references to them are sorted
+    /// (which doesn't require the lockfile to be mutable).
+    pub fn iter_sorted(&self) -> impl Iterator<Item = &Package> {
+        let mut packages = self.packages.iter().collect::<Vec<_>>();
+        packages.sort();
+
+        packages.into_iter()
+    }
+
+    /// Find the packages with the given name, tolerating differences in case
+    /// and between hyphens and underscores (e.g. finding `serde_derive` when
+    /// looking for `serde-derive`), which is how users often misremember
+    /// crate names.
+    ///
+    /// Exact matches take precedence: if any package has exactly the given
+    /// name, only those packages are returned. Otherwise all packages whose
+    /// names match approximately are, which may have several different
+    /// names. Packages are returned in the order of [`Lockfile::packages`].
+    pub fn find_package_fuzzy(&self, query: &str) -> Vec<&Package> {
+        let exact = self
+            .packages
+            .iter()
+            .filter(|pkg| pkg.name.as_str() == query)
+            .collect::<Vec<_>>();
+
+        if !exact.is_empty() {
+            return exact;
+        }
+
+        let normalize = |name: &str| name.to_ascii_lowercase().replace('-', "_");
+        let query = normalize(query);
+
+        self.packages
+            .iter()
+            .filter(|pkg| normalize(pkg.name.as_str()) == query)
+            .collect()
+    }
+
+    /// Get a summary of each of this lockfile's packages (i.e. its name,
+    /// version, and source) as a [`Dependency`] on it, in the order of
+    /// [`Lockfile::packages`].
+    ///
+    /// These are what `cargo lock list` prints (one per line, as formatted
+    /// by their [`Display`] impl), for tools which want to build on the
+    /// same summary.
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub fn summaries(&self) -> Vec<Dependency> {
+        self.summaries_filtered(|_| true)
+    }
+
+    /// Get summaries (see [`Lockfile::summaries`]) of only those packages
+    /// for which the given predicate returns `true`, in the order of
+    /// [`Lockfile::packages`].
+    pub fn summaries_filtered<F>(&self, mut predicate: F) -> Vec<Dependency>
+    where
+        F: FnMut(&Package) -> bool,
+    {
+        self.packages
+            .iter()
+            .filter(|package| predicate(package))
+            .map(Dependency::from)
+            .collect()
+    }
+
+    /// Remove the package with the given name and version from this
+    /// lockfile, returning it if it was present. If there are several such
+    /// packages (from different sources), the first one is removed.
+    ///
+    /// Dependencies of other packages on the removed package are left in
+    /// place, which makes the lockfile invalid: use
+    /// [`Lockfile::dangling_dependencies`] to find them, or
+    /// [`Lockfile::prune_dangling_dependencies`] to remove them.
+    pub fn remove_package(&mut self, name: &Name, version: &Version) -> Option<Package> {
+        let index = self
+            .packages
+            .iter()
+            .position(|pkg| &pkg.name == name && &pkg.version == version)?;
+
+        Some(self.packages.remove(index))
+    }
+
+    /// Find dependencies (of packages and the legacy root package) which
+    /// don't refer to any package in this lockfile, e.g. after calling
+    /// [`Lockfile::remove_package`].
+    pub fn dangling_dependencies(&self) -> Vec<(&Package, &Dependency)> {
+        let present = self
+            .packages
+            .iter()
+            .map(Dependency::from)
+            .collect::<HashSet<_>>();
+
+        self.iter_dependencies()
+            .filter(|(_, dependency)| !present.contains(dependency))
+            .collect()
+    }
+
+    /// Resolve the dependencies of the given package to the packages in this
+    /// lockfile they refer to, in the same order.
+    ///
+    /// Dependencies are matched by name, version, and source, i.e. the same
+    /// way as when computing the dependency tree. It's an
+    /// [`ErrorKind::DanglingDependency`] error if a dependency doesn't refer
+    /// to any package, and an [`ErrorKind::DuplicatePackage`] error if it
+    /// refers to more than one (i.e. to a package listed more than once).
+    pub fn resolve_dependencies(&self, package: &Package) -> Result<Vec<&Package>, Error> {
+        package
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                let mut matching = self
+                    .packages
+                    .iter()
+                    .filter(|&pkg| Dependency::from(pkg) == *dependency);
+
+                match (matching.next(), matching.next()) {
+                    (Some(resolved), None) => Ok(resolved),
+                    (None, _) => fail!(
+                        ErrorKind::DanglingDependency,
+                        "couldn't resolve dependency of {}: {}",
+                        package,
+                        dependency
+                    ),
+                    (Some(_), Some(_)) => fail!(
+                        ErrorKind::DuplicatePackage,
+                        "dependency of {} refers to more than one package: {}",
+                        package,
+                        dependency
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Get the package declared by the legacy `[root]` table of old V1
+    /// lockfiles, if any.
+    ///
+    /// If the same package (i.e. name, version, and source) is also listed
+    /// as a `[[package]]`, that entry is returned. Otherwise (as in lockfiles
+    /// written by Cargo, which never listed the root package twice) it's
+    /// [`Lockfile::root`] itself. Newer lockfiles don't have a `[root]`
+    /// table, so this is `None` for them: use [`Lockfile::root_packages`] to
+    /// find the packages nothing else depends on instead.
+    pub fn root_package(&self) -> Option<&Package> {
+        let root = self.root.as_ref()?;
+        let id = PackageId::from(root);
+
+        self.packages
+            .iter()
+            .find(|package| PackageId::from(*package) == id)
+            .or(Some(root))
+    }
+
+    /// Find the root packages of the dependency graph, i.e. the packages no
+    /// other package depends on (such as the members of a workspace).
+    ///
+    /// Packages which only depend on themselves are still roots, while the
+    /// legacy `[root]` package (if any) isn't considered part of the graph.
+    /// It's an [`ErrorKind::MissingRoots`] error if there are no roots, i.e.
+    /// if the lockfile is empty or every package is part of a dependency cycle.
+    pub fn root_packages(&self) -> Result<Vec<&Package>, Error> {
+        let mut depended_upon = HashSet::new();
+
+        for package in &self.packages {
+            let this = Dependency::from(package);
+            depended_upon.extend(package.dependencies.iter().filter(|&dep| *dep != this));
+        }
+
+        let roots = self
+            .packages
+            .iter()
+            .filter(|package| !depended_upon.contains(&Dependency::from(*package)))
+            .collect::<Vec<_>>();
+
+        if roots.is_empty() {
+            if self.packages.is_empty() {
+                fail!(
+                    ErrorKind::MissingRoots,
+                    "couldn't identify root packages: lockfile has no packages"
+                );
+            }
+
+            fail!(
+                ErrorKind::MissingRoots,
+                "couldn't identify root packages: every package is depended upon by another one (i.e. the dependency graph is cyclic)"
+            );
+        }
+
+        Ok(roots)
+    }
+
+    /// Remove all dependencies which don't refer to any package in this
+    /// lockfile (see [`Lockfile::dangling_dependencies`]), returning the
+    /// number of dependencies removed.
+    pub fn prune_dangling_dependencies(&mut self) -> usize {
+        let present = self
+            .packages
+            .iter()
+            .map(Dependency::from)
+            .collect::<HashSet<_>>();
+
+        let mut pruned = 0;
+
+        for package in self.packages.iter_mut().chain(self.root.as_mut()) {
+            let before = package.dependencies.len();
+            package
+                .dependencies
+                .retain(|dependency| present.contains(dependency));
+            pruned += before - package.dependencies.len();
+        }
+
+        pruned
+    }
+
+    /// Apply a `[patch]` override to the resolved packages as Cargo would,
+    /// e.g. to preview its effect without running Cargo. Returns the IDs of
+    /// the packages which were replaced.
+    ///
+    /// Packages from the patched `source` with the same name as the
+    /// `replacement` and a semver-compatible version are replaced by it, and
+    /// dependencies on them are rewritten to refer to it instead. The
+    /// crates.io index matches regardless of whether it's accessed via git or
+    /// the sparse protocol. The replacement's own dependencies are taken as
+    /// given, so they should refer to packages in this lockfile.
+    ///
+    /// It's an [`ErrorKind::UnusedPatch`] error (which leaves this lockfile
+    /// unchanged) if the patch doesn't match any package.
+    pub fn apply_patch(
+        &mut self,
+        source: &SourceId,
+        replacement: &Package,
+    ) -> Result<Vec<PackageId>, Error> {
+        let source = source.with_precise(None).normalize_crates_io();
+        let replaced = self
+            .packages
+            .iter()
+            .filter(|package| {
+                package.name == replacement.name
+                    && package
+                        .source
+                        .as_ref()
+                        .map(|s| s.with_precise(None).normalize_crates_io())
+                        .as_ref()
+                        == Some(&source)
+                    && is_compatible(&package.version, &replacement.version)
+            })
+            .map(PackageId::from)
+            .collect::<Vec<_>>();
+
+        if replaced.is_empty() {
+            fail!(
+                ErrorKind::UnusedPatch,
+                "patch for `{}` doesn't match any package from {}",
+                replacement.name,
+                source
+            );
         }
+
+        self.packages
+            .retain(|package| !replaced.contains(&PackageId::from(package)));
+
+        for package in self.packages.iter_mut().chain(self.root.as_mut()) {
+            for dependency in package.dependencies.iter_mut().chain(&mut package.replace) {
+                if replaced.contains(&PackageId::from(&*dependency)) {
+                    *dependency = Dependency::from(replacement);
+                }
+            }
+        }
+
+        let id = PackageId::from(replacement);
+
+        if !self
+            .packages
+            .iter()
+            .any(|package| PackageId::from(package) == id)
+        {
+            self.packages.push(replacement.clone());
+        }
+
+        self.patch
+            .unused
+            .retain(|unused| PackageId::from(unused) != id);
+
+        self.packages.sort();
+
+        Ok(replaced)
+    }
+
+    /// Is the package with the given name overridden by one of the given
+    /// [`Manifest`]'s `[patch]` entries (e.g. in `[patch.crates-io]`)?
+    ///
+    /// `Cargo.lock` only records `[patch]` entries which *aren't* used
+    /// (see [`Patch::unused`]), so patches are read from the manifest. A
+    /// package is patched if there's a patch for it which isn't listed as
+    /// unused and it's present in this lockfile.
+    pub fn is_patched(&self, manifest: &Manifest, name: &Name) -> bool {
+        let patched = manifest
+            .patches()
+            .flat_map(|(_, deps)| deps)
+            .any(|dep| dep.package_name() == name);
+
+        patched
+            && self.packages.iter().any(|pkg| &pkg.name == name)
+            && !self.patch.unused.iter().any(|unused| &unused.name == name)
+    }
+
+    /// Move V1-style checksums from the `[metadata]` table onto their
+    /// packages, returning the number of checksums moved.
+    ///
+    /// This repairs lockfiles which declare a newer [`ResolveVersion`] but
+    /// still keep their checksums in `[metadata]`. Entries which conflict
+    /// with a package's existing checksum, or which don't refer to any
+    /// package, are kept as-is.
+    pub fn inline_metadata_checksums(&mut self) -> usize {
+        let packages = &mut self.packages;
+        let mut moved = 0;
+
+        self.metadata.retain(|key, value| {
+            let (dependency, checksum) = match (key.checksum_dependency(), value.checksum()) {
+                (Ok(dependency), Ok(checksum)) => (dependency, checksum),
+                _ => return true,
+            };
+
+            let package = packages.iter_mut().find(|pkg| {
+                dependency.matches(pkg)
+                    && dependency.source.as_ref().map(|s| s.with_precise(None))
+                        == pkg.source.as_ref().map(|s| s.with_precise(None))
+            });
+
+            match package {
+                Some(package) if package.checksum.is_none() => {
+                    package.checksum = Some(checksum);
+                }
+                Some(package) if package.checksum.as_ref() == Some(&checksum) => (),
+                _ => return true,
+            }
+
+            moved += 1;
+            false
+        });
+
+        moved
+    }
+
+    /// Get a copy of this lockfile with all checksums removed (both those of
+    /// packages and V1-style `[metadata]` checksum entries).
+    ///
+    /// Useful for comparing the logical dependency set of lockfiles resolved
+    /// against registries (e.g. mirrors) which compute different checksums.
+    pub fn without_checksums(&self) -> Self {
+        let mut lockfile = self.clone();
+
+        for package in lockfile.packages.iter_mut().chain(lockfile.root.as_mut()) {
+            package.checksum = None;
+        }
+
+        lockfile.metadata.retain(|key, _| !key.is_checksum());
+        lockfile
+    }
+
+    /// Find the direct dependencies declared in the given [`Manifest`] which
+    /// aren't satisfied by any package in this lockfile, i.e. which
+    /// `cargo build --locked` would fail to resolve.
+    ///
+    /// Renamed dependencies are matched by the name of the package they
+    /// refer to. Dependencies without a version requirement (e.g. path or
+    /// git dependencies) are satisfied by any package with their name.
+    /// Dependencies inherited from the workspace can't be checked, since
+    /// their requirements are declared in the workspace's manifest, and are
+    /// reported as [`Unsatisfied::WorkspaceInherited`].
+    pub fn unsatisfied_dependencies<'m>(
+        &self,
+        manifest: &'m Manifest,
+    ) -> Vec<(&'m manifest::Dependency, Unsatisfied)> {
+        manifest
+            .dependencies
+            .iter()
+            .filter_map(|dependency| {
+                if dependency.workspace {
+                    return Some((dependency, Unsatisfied::WorkspaceInherited));
+                }
+
+                let satisfied = self.packages.iter().any(|package| {
+                    package.name == *dependency.package_name()
+                        && dependency
+                            .req
+                            .as_ref()
+                            .map_or(true, |req| req.matches(&package.version))
+                });
+
+                if satisfied {
+                    None
+                } else {
+                    Some((dependency, Unsatisfied::Missing))
+                }
+            })
+            .collect()
+    }
+
+    /// Get the packages required by the root package of the given
+    /// [`Manifest`] when only following its direct dependencies of the given
+    /// `kinds`, e.g. to exclude packages which are only present because they
+    /// are dev or build dependencies of the root package.
+    ///
+    /// `Cargo.lock` files don't record the kinds of dependencies, so this
+    /// requires the root package's `Cargo.toml`. Only the root package's own
+    /// dependencies are classified: all dependencies of other packages are
+    /// always followed. The root package itself is included in the result.
+    ///
+    /// It's an [`ErrorKind::MissingRoots`] error if the manifest is a virtual
+    /// manifest, and an [`ErrorKind::PackageNotFound`] error if its package
+    /// isn't in this lockfile.
+    pub fn packages_for_dependency_kinds(
+        &self,
+        manifest: &Manifest,
+        kinds: &[DependencyKind],
+    ) -> Result<Vec<&Package>, Error> {
+        let root_name = manifest.package.as_ref().ok_or_else(|| {
+            format_err!(
+                ErrorKind::MissingRoots,
+                "manifest has no [package] section (virtual manifests are unsupported)"
+            )
+        })?;
+
+        let root = self
+            .packages
+            .iter()
+            .position(|pkg| pkg.name == *root_name && pkg.source.is_none())
+            .ok_or_else(|| {
+                format_err!(
+                    ErrorKind::PackageNotFound,
+                    "package `{}` not found in lockfile",
+                    root_name
+                )
+            })?;
+
+        let names = manifest
+            .dependencies
+            .iter()
+            .filter(|dep| kinds.contains(&dep.kind))
+            .map(|dep| dep.package_name())
+            .collect::<Vec<_>>();
+
+        let mut reachable = vec![false; self.packages.len()];
+        reachable[root] = true;
+
+        let mut queue = self.packages[root]
+            .dependencies
+            .iter()
+            .filter(|dep| names.contains(&&dep.name))
+            .collect::<Vec<_>>();
+
+        while let Some(dependency) = queue.pop() {
+            if let Some(index) = self.position_of(dependency) {
+                if !reachable[index] {
+                    reachable[index] = true;
+                    queue.extend(&self.packages[index].dependencies);
+                }
+            }
+        }
+
+        Ok(self
+            .packages
+            .iter()
+            .zip(reachable)
+            .filter_map(|(package, reachable)| if reachable { Some(package) } else { None })
+            .collect())
+    }
+
+    /// Get the names of all packages the package with the given name and
+    /// version depends on, directly or transitively.
+    ///
+    /// Dependency cycles are followed only once. The starting package's own
+    /// name is only included if it depends on another version of itself.
+    /// It's an [`ErrorKind::PackageNotFound`] error if no such package is
+    /// present.
+    pub fn transitive_dependencies(
+        &self,
+        name: &Name,
+        version: &Version,
+    ) -> Result<BTreeSet<Name>, Error> {
+        let start = self
+            .packages
+            .iter()
+            .position(|pkg| &pkg.name == name && &pkg.version == version)
+            .ok_or_else(|| {
+                format_err!(
+                    ErrorKind::PackageNotFound,
+                    "package `{} {}` not found in lockfile",
+                    name,
+                    version
+                )
+            })?;
+
+        let mut visited = vec![false; self.packages.len()];
+        visited[start] = true;
+
+        let mut queue = self.packages[start].dependencies.iter().collect::<Vec<_>>();
+        let mut names = BTreeSet::new();
+
+        while let Some(dependency) = queue.pop() {
+            if let Some(index) = self.position_of(dependency) {
+                if !visited[index] {
+                    visited[index] = true;
+                    names.insert(self.packages[index].name.clone());
+                    queue.extend(&self.packages[index].dependencies);
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Find the index of the package matching the given [`Dependency`]
+    fn position_of(&self, dependency: &Dependency) -> Option<usize> {
+        self.packages.iter().position(|pkg| dependency.matches(pkg))
     }
 
     /// Get the dependency tree for this `Lockfile`. Returns an error if the
@@ -59,18 +1230,265 @@ impl Lockfile {
     pub fn dependency_tree(&self) -> Result<Tree, Error> {
         Tree::new(self)
     }
+
+    /// Get every dependency path from a root package (i.e. one which no
+    /// other package depends upon) to the packages named `target`, e.g. to
+    /// find out which direct dependencies pull in a crate with a security
+    /// advisory. See [`Tree::paths_to`] for how paths are found.
+    ///
+    /// Paths start with the root and end with `target`, and never contain
+    /// the same package twice. Paths to all versions of `target` are
+    /// included, and none are returned if there's no such package.
+    ///
+    /// The `dependency-tree` Cargo feature must be enabled to use this.
+    #[cfg(feature = "dependency-tree")]
+    pub fn dependency_paths(&self, target: &Name) -> Result<Vec<Vec<Name>>, Error> {
+        let tree = self.dependency_tree()?;
+        let graph = tree.graph();
+        let mut paths = vec![];
+
+        for package in self.packages.iter().filter(|pkg| &pkg.name == target) {
+            let index = tree.nodes()[&Dependency::from(package)];
+
+            paths.extend(tree.paths_to(index, None).into_iter().map(|path| {
+                path.into_iter()
+                    .map(|index| graph[index].name.clone())
+                    .collect()
+            }));
+        }
+
+        Ok(paths)
+    }
+
+    /// Find the shortest dependency path from a package named `from` to one
+    /// named `to` (see [`Tree::find_path`]), considering all versions of
+    /// both, e.g. to find out how two packages end up connected. Returns
+    /// `None` if no package named `from` depends on one named `to`.
+    ///
+    /// It's an [`ErrorKind::PackageNotFound`] error if there's no package
+    /// with either name.
+    ///
+    /// The `dependency-tree` Cargo feature must be enabled to use this.
+    #[cfg(feature = "dependency-tree")]
+    pub fn find_path(&self, from: &Name, to: &Name) -> Result<Option<Vec<&Package>>, Error> {
+        let tree = self.dependency_tree()?;
+        let nodes = |name: &Name| -> Result<Vec<_>, Error> {
+            let indexes = self
+                .packages
+                .iter()
+                .filter(|pkg| &pkg.name == name)
+                .map(|pkg| tree.nodes()[&Dependency::from(pkg)])
+                .collect::<Vec<_>>();
+
+            if indexes.is_empty() {
+                fail!(
+                    ErrorKind::PackageNotFound,
+                    "package `{}` not found in lockfile",
+                    name
+                );
+            }
+
+            Ok(indexes)
+        };
+
+        let (from, to) = (nodes(from)?, nodes(to)?);
+        let mut shortest: Option<Vec<_>> = None;
+
+        for &from in &from {
+            for &to in &to {
+                if let Some(path) = tree.find_path(from, to) {
+                    if shortest.as_ref().map_or(true, |s| path.len() < s.len()) {
+                        shortest = Some(path);
+                    }
+                }
+            }
+        }
+
+        Ok(shortest.map(|path| {
+            path.into_iter()
+                .map(|index| {
+                    let dependency = Dependency::from(&tree.graph()[index]);
+                    self.packages
+                        .iter()
+                        .find(|&pkg| Dependency::from(pkg) == dependency)
+                        .unwrap()
+                })
+                .collect()
+        }))
+    }
+
+    /// Find packages which aren't reachable from any root, e.g. orphaned
+    /// entries left behind after a dependency was removed.
+    ///
+    /// The roots are the dependencies of the legacy `[root]` package if
+    /// present, and otherwise all packages without a source (i.e. workspace
+    /// members and path dependencies). It's an [`ErrorKind::MissingRoots`]
+    /// error if there are no roots, since all packages would then be
+    /// reported.
+    ///
+    /// The `dependency-tree` Cargo feature must be enabled to use this.
+    #[cfg(feature = "dependency-tree")]
+    pub fn unused_packages(&self) -> Result<Vec<&Package>, Error> {
+        let tree = self.dependency_tree()?;
+        let nodes = tree.nodes();
+
+        let roots = match &self.root {
+            Some(root) => root
+                .dependencies
+                .iter()
+                .filter_map(|dependency| nodes.get(dependency).copied())
+                .collect::<Vec<_>>(),
+            None => self
+                .packages
+                .iter()
+                .filter(|package| package.source.is_none())
+                .map(|package| nodes[&Dependency::from(package)])
+                .collect(),
+        };
+
+        if self.root.is_none() && roots.is_empty() {
+            fail!(
+                ErrorKind::MissingRoots,
+                "couldn't identify root packages: lockfile has no [root] package or workspace members"
+            );
+        }
+
+        let mut dfs = Dfs::empty(tree.graph());
+
+        for root in roots {
+            dfs.move_to(root);
+            while dfs.next(tree.graph()).is_some() {}
+        }
+
+        Ok(self
+            .packages
+            .iter()
+            .filter(|package| !dfs.discovered[nodes[&Dependency::from(*package)].index()])
+            .collect())
+    }
+
+    /// Find the packages which would no longer be needed without the package
+    /// with the given name (in all of its versions), e.g. to find out how
+    /// much the dependency tree would shrink when dropping a dependency.
+    ///
+    /// Returns the names of all packages reachable from the root packages
+    /// (see [`Lockfile::root_packages`]) which are only reachable via the
+    /// given package, not including the given package itself. It's an
+    /// [`ErrorKind::PackageNotFound`] error if there's no package with the
+    /// given name.
+    ///
+    /// The `dependency-tree` Cargo feature must be enabled to use this.
+    #[cfg(feature = "dependency-tree")]
+    pub fn packages_removable_with(&self, name: &Name) -> Result<BTreeSet<Name>, Error> {
+        let tree = self.dependency_tree()?;
+        let nodes = tree.nodes();
+        let roots = self.root_packages()?;
+
+        let targets = self
+            .packages
+            .iter()
+            .filter(|package| &package.name == name)
+            .map(|package| nodes[&Dependency::from(package)])
+            .collect::<Vec<_>>();
+
+        if targets.is_empty() {
+            fail!(
+                ErrorKind::PackageNotFound,
+                "package `{}` not found in lockfile",
+                name
+            );
+        }
+
+        // Find what's reachable, with and without traversing the targets
+        let reachable = |skip_targets: bool| {
+            let mut dfs = Dfs::empty(tree.graph());
+
+            if skip_targets {
+                for &target in &targets {
+                    dfs.discovered.insert(target.index());
+                }
+            }
+
+            for root in &roots {
+                let root = nodes[&Dependency::from(*root)];
+
+                if !dfs.discovered[root.index()] {
+                    dfs.move_to(root);
+                    while dfs.next(tree.graph()).is_some() {}
+                }
+            }
+
+            dfs.discovered
+        };
+
+        let (with, without) = (reachable(false), reachable(true));
+
+        Ok(self
+            .packages
+            .iter()
+            .filter(|package| &package.name != name)
+            .filter(|package| {
+                let index = nodes[&Dependency::from(*package)].index();
+                with[index] && !without[index]
+            })
+            .map(|package| package.name.clone())
+            .collect())
+    }
 }
 
+/// Parses a lockfile from an in-memory string, e.g. one fetched over the
+/// network (see [`Lockfile::load`] for loading one from a file).
+///
+/// All errors are reported using this crate's [`Error`] type, so failure
+/// modes can be distinguished by their [`ErrorKind`]:
+///
+/// - [`ErrorKind::Parse`]: invalid TOML, or invalid or missing fields (see
+///   [`Error::line_col`] for the location in the input)
+/// - [`ErrorKind::UnsupportedVersion`]: unknown `version` field
+/// - [`ErrorKind::MissingPackages`]: no `[[package]]` entries or `version`
+///   field at all, e.g. because the input was truncated. Lockfiles which
+///   only have a `version` field are valid, and have no packages.
+/// - [`ErrorKind::NotALockfile`]: the input is a `Cargo.toml` manifest
+/// - [`ErrorKind::DuplicatePackage`] and [`ErrorKind::DanglingDependency`]:
+///   the packages are inconsistent with each other
+///
+/// ```
+/// use cargo_lock::{ErrorKind, Lockfile};
+/// use std::str::FromStr;
+///
+/// let err = Lockfile::from_str("# truncated\n").unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::MissingPackages);
+///
+/// let lockfile = Lockfile::from_str("version = 3\n").unwrap();
+/// assert!(lockfile.packages.is_empty());
+/// ```
 impl FromStr for Lockfile {
     type Err = Error;
 
     fn from_str(toml_string: &str) -> Result<Self, Error> {
-        Ok(toml::from_str(toml_string)?)
+        // Tolerate a leading byte order mark (see `SerializeOptions::bom`)
+        let toml_string = toml_string.trim_start_matches('\u{feff}');
+        let mut raw_lockfile = EncodableLockfile::parse(toml_string)?;
+        raw_lockfile.preamble = EncodableLockfile::parse_preamble(toml_string);
+
+        // Convert outside of `Deserialize` to preserve the `ErrorKind`
+        raw_lockfile.try_into().map_err(|e: Error| {
+            if e.kind() == ErrorKind::MissingPackages {
+                if let Err(e) = EncodableLockfile::check_not_manifest(toml_string) {
+                    return e;
+                }
+            }
+
+            e
+        })
     }
 }
 
-impl ToString for Lockfile {
-    fn to_string(&self) -> String {
-        toml::to_string(&EncodableLockfile::from(self)).unwrap()
+/// Formats the lockfile exactly the same way as Cargo does (see
+/// [`Lockfile::write_to`]), so an untouched lockfile round-trips
+/// byte-for-byte.
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_with_options(&SerializeOptions::default()))
     }
 }
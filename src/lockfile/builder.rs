@@ -0,0 +1,101 @@
+//! Builder for constructing lockfiles in code
+
+use super::{Lockfile, ResolveVersion, ValidationError};
+use crate::{
+    metadata::{self, Metadata},
+    package::Package,
+    patch::Patch,
+};
+
+/// Builder for constructing a [`Lockfile`] in code, e.g. synthetic
+/// lockfiles for tests, as an alternative to setting its fields directly.
+///
+/// ```
+/// use cargo_lock::{LockfileBuilder, Package};
+///
+/// let package = Package {
+///     name: "example".parse().unwrap(),
+///     version: "0.1.0".parse().unwrap(),
+///     source: None,
+///     checksum: None,
+///     dependencies: vec![],
+///     replace: None,
+/// };
+///
+/// let lockfile = LockfileBuilder::new().package(package).build().unwrap();
+/// assert_eq!(lockfile.packages.len(), 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LockfileBuilder {
+    /// Explicitly set resolve version (detected if `None`)
+    version: Option<ResolveVersion>,
+
+    /// Packages added so far
+    packages: Vec<Package>,
+
+    /// Metadata added so far
+    metadata: Metadata,
+
+    /// Patches added so far
+    patch: Patch,
+}
+
+impl LockfileBuilder {
+    /// Create a new builder for an empty lockfile
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a package
+    pub fn package(mut self, package: Package) -> Self {
+        self.packages.push(package);
+        self
+    }
+
+    /// Add an entry to the `[metadata]` table
+    pub fn metadata(mut self, key: metadata::Key, value: metadata::Value) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Add an unused patch (see [`Patch::unused`])
+    pub fn patch(mut self, unused: Package) -> Self {
+        self.patch.unused.push(unused);
+        self
+    }
+
+    /// Set the resolve version, rather than detecting it from the packages
+    /// and metadata like for lockfiles without an explicit `version` field
+    /// (i.e. V1 if there are checksums in `[metadata]`, and V2 otherwise)
+    pub fn resolve_version(mut self, version: ResolveVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Build the lockfile, sorting it (see [`Lockfile::sort`]) and checking
+    /// it for problems (see [`Lockfile::validate`]).
+    pub fn build(self) -> Result<Lockfile, Vec<ValidationError>> {
+        // Parsed V1 lockfiles have checksums in both `[metadata]` and their
+        // packages, so (unlike when parsing) only the former is significant
+        let version = self.version.unwrap_or_else(|| {
+            if self.metadata.keys().any(metadata::Key::is_checksum) {
+                ResolveVersion::V1
+            } else {
+                ResolveVersion::V2
+            }
+        });
+
+        let mut lockfile = Lockfile {
+            version,
+            packages: self.packages,
+            root: None,
+            metadata: self.metadata,
+            patch: self.patch,
+            preamble: None,
+        };
+
+        lockfile.sort();
+        lockfile.validate()?;
+        Ok(lockfile)
+    }
+}
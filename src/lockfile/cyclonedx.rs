@@ -0,0 +1,143 @@
+//! CycloneDX software bill of materials (SBOM) export
+
+use super::{
+    sbom::{self, SbomOptions},
+    Lockfile,
+};
+use crate::package::Package;
+
+/// Version of the CycloneDX specification documents are generated for
+const SPEC_VERSION: &str = "1.4";
+
+impl Lockfile {
+    /// Generate a minimal [CycloneDX] SBOM (as JSON) listing the packages in
+    /// this lockfile.
+    ///
+    /// Each package is a `library` component with a `pkg:cargo/<name>@<version>`
+    /// [package URL] and its checksum (if any) as a SHA-256 hash. Packages
+    /// which aren't from crates.io have their source recorded in the package
+    /// URL's `repository_url` (other registries), `vcs_url` (git, including
+    /// the locked commit), or `download_url` (local sources) qualifier.
+    /// Components are sorted by name, version, and source, so the output is
+    /// deterministic.
+    ///
+    /// The `cyclonedx` Cargo feature must be enabled to use this.
+    ///
+    /// [CycloneDX]: https://cyclonedx.org/
+    /// [package URL]: https://github.com/package-url/purl-spec
+    pub fn to_cyclonedx(&self) -> String {
+        self.to_cyclonedx_with_options(&SbomOptions::default())
+    }
+
+    /// Generate a [CycloneDX] SBOM like [`Lockfile::to_cyclonedx`], using
+    /// the given options (e.g. to treat a mirror as crates.io).
+    ///
+    /// [CycloneDX]: https://cyclonedx.org/
+    pub fn to_cyclonedx_with_options(&self, options: &SbomOptions) -> String {
+        let mut packages = self.packages.iter().collect::<Vec<_>>();
+        packages.sort();
+
+        let mut out = String::from("{\n");
+        out.push_str("  \"bomFormat\": \"CycloneDX\",\n");
+        out.push_str(&format!("  \"specVersion\": \"{}\",\n", SPEC_VERSION));
+        out.push_str("  \"version\": 1,\n");
+        out.push_str("  \"components\": [");
+
+        for (i, package) in packages.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            push_component(&mut out, package, options);
+        }
+
+        if !packages.is_empty() {
+            out.push_str("\n  ");
+        }
+
+        out.push_str("]\n}\n");
+        out
+    }
+}
+
+/// Push the JSON object describing the component for the given package
+fn push_component(out: &mut String, package: &Package, options: &SbomOptions) {
+    let purl = sbom::purl(package, options);
+
+    out.push_str("\n    {\n");
+    out.push_str("      \"type\": \"library\",\n");
+    out.push_str(&format!("      \"bom-ref\": {},\n", json_string(&purl)));
+    out.push_str(&format!(
+        "      \"name\": {},\n",
+        json_string(package.name.as_str())
+    ));
+    out.push_str(&format!(
+        "      \"version\": {},\n",
+        json_string(&package.version.to_string())
+    ));
+    out.push_str(&format!("      \"purl\": {}", json_string(&purl)));
+
+    if let Some(checksum) = &package.checksum {
+        out.push_str(",\n      \"hashes\": [\n");
+        out.push_str("        {\n");
+        out.push_str("          \"alg\": \"SHA-256\",\n");
+        out.push_str(&format!("          \"content\": \"{:x}\"\n", checksum));
+        out.push_str("        }\n");
+        out.push_str("      ]");
+    }
+
+    out.push_str("\n    }");
+}
+
+/// Encode a string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lockfile;
+
+    #[test]
+    fn cyclonedx_components() {
+        let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        let sbom = lockfile.to_cyclonedx();
+
+        assert!(sbom.starts_with("{\n  \"bomFormat\": \"CycloneDX\",\n"));
+        assert!(sbom.contains("\"purl\": \"pkg:cargo/app@0.1.0\"\n"));
+        assert!(sbom.contains(
+            "\"purl\": \"pkg:cargo/master-branch@0.3.0?vcs_url=git%2Bhttps%3A%2F%2Fgithub.com%2Fexample%2Fmaster-branch%409f8e7d6c5b4a39281706f5e4d3c2b1a098765432\""
+        ));
+        assert!(sbom.contains(
+            "\"content\": \"414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449\""
+        ));
+
+        // Components are sorted regardless of the order of packages
+        let mut reversed = lockfile.clone();
+        reversed.packages.reverse();
+        assert_eq!(reversed.to_cyclonedx(), sbom);
+    }
+
+    #[test]
+    fn empty_cyclonedx() {
+        let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        lockfile.packages.clear();
+        assert!(lockfile.to_cyclonedx().ends_with("\"components\": []\n}\n"));
+    }
+}
@@ -0,0 +1,237 @@
+//! Differences between lockfiles
+
+use super::Lockfile;
+use crate::package::{Name, Package, PackageId, SourceId, Version};
+use std::collections::BTreeMap as Map;
+
+/// Differences between two lockfiles (see [`Lockfile::diff`])
+///
+/// All changes are sorted by package name (and then version).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LockfileDiff {
+    /// Packages which are only present in the new lockfile
+    pub added: Vec<PackageId>,
+
+    /// Packages which are only present in the old lockfile
+    pub removed: Vec<PackageId>,
+
+    /// Packages whose version changed
+    pub version_changed: Vec<VersionChange>,
+
+    /// Packages whose version stayed the same but whose source changed,
+    /// e.g. a crate from crates.io which was redirected to a git fork
+    pub source_changed: Vec<SourceChange>,
+}
+
+impl LockfileDiff {
+    /// Compute the differences between the `old` and `new` lockfiles.
+    ///
+    /// Packages are matched up by name. When a name has a single package in
+    /// both lockfiles, which differ in version, it's a version change. When
+    /// packages of a given name have the same version but different sources,
+    /// it's a source change. All other differences are additions/removals.
+    pub fn new(old: &Lockfile, new: &Lockfile) -> Self {
+        let mut diff = Self::default();
+        let old_packages = by_name(old);
+        let new_packages = by_name(new);
+
+        for (name, old_packages) in &old_packages {
+            let new_packages = match new_packages.get(name) {
+                Some(packages) => packages,
+                None => {
+                    diff.removed
+                        .extend(old_packages.iter().map(|&pkg| PackageId::from(pkg)));
+                    continue;
+                }
+            };
+
+            let mut old_only = old_packages
+                .iter()
+                .filter(|&&old_pkg| !new_packages.iter().any(|&pkg| same(old_pkg, pkg)))
+                .copied()
+                .collect::<Vec<_>>();
+
+            let mut new_only = new_packages
+                .iter()
+                .filter(|&&new_pkg| !old_packages.iter().any(|&pkg| same(new_pkg, pkg)))
+                .copied()
+                .collect::<Vec<_>>();
+
+            old_only.retain(|old_pkg| {
+                match new_only
+                    .iter()
+                    .position(|new_pkg| new_pkg.version == old_pkg.version)
+                {
+                    Some(index) => {
+                        let new_pkg = new_only.remove(index);
+                        diff.source_changed.push(SourceChange {
+                            name: (*name).clone(),
+                            version: old_pkg.version.clone(),
+                            old: old_pkg.source.clone(),
+                            new: new_pkg.source.clone(),
+                        });
+                        false
+                    }
+                    None => true,
+                }
+            });
+
+            if let ([old_pkg], [new_pkg]) = (old_only.as_slice(), new_only.as_slice()) {
+                diff.version_changed.push(VersionChange {
+                    name: (*name).clone(),
+                    old: old_pkg.version.clone(),
+                    new: new_pkg.version.clone(),
+                });
+            } else {
+                diff.removed
+                    .extend(old_only.iter().map(|&pkg| PackageId::from(pkg)));
+                diff.added
+                    .extend(new_only.iter().map(|&pkg| PackageId::from(pkg)));
+            }
+        }
+
+        for (name, new_packages) in &new_packages {
+            if !old_packages.contains_key(name) {
+                diff.added
+                    .extend(new_packages.iter().map(|&pkg| PackageId::from(pkg)));
+            }
+        }
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff
+    }
+
+    /// Are the lockfiles free of differences?
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.version_changed.is_empty()
+            && self.source_changed.is_empty()
+    }
+}
+
+/// Change of a package's version
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionChange {
+    /// Name of the package
+    pub name: Name,
+
+    /// Version in the old lockfile
+    pub old: Version,
+
+    /// Version in the new lockfile
+    pub new: Version,
+}
+
+/// Change of a package's source without a change of its version
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceChange {
+    /// Name of the package
+    pub name: Name,
+
+    /// Version of the package
+    pub version: Version,
+
+    /// Source in the old lockfile
+    pub old: Option<SourceId>,
+
+    /// Source in the new lockfile
+    pub new: Option<SourceId>,
+}
+
+/// Differences between two lockfiles as a flat list of classified changes
+/// (see [`Lockfile::changeset`])
+///
+/// Changes are sorted by package name, so all changes of a crate are next
+/// to each other.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Changeset {
+    /// Changes between the previous and the current lockfile
+    pub changes: Vec<Change>,
+}
+
+impl Changeset {
+    /// Are the lockfiles free of differences?
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Iterate over the changes
+    pub fn iter(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter()
+    }
+}
+
+impl From<LockfileDiff> for Changeset {
+    fn from(diff: LockfileDiff) -> Changeset {
+        let mut changes = diff
+            .removed
+            .into_iter()
+            .map(Change::Removed)
+            .chain(diff.added.into_iter().map(Change::Added))
+            .chain(diff.version_changed.into_iter().map(|change| {
+                if change.new > change.old {
+                    Change::Upgraded(change)
+                } else {
+                    Change::Downgraded(change)
+                }
+            }))
+            .chain(diff.source_changed.into_iter().map(Change::SourceChanged))
+            .collect::<Vec<_>>();
+
+        // Stable, so the above order is kept for changes of the same crate
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        Changeset { changes }
+    }
+}
+
+/// Classified change of a package between two lockfiles
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change {
+    /// Package which is only present in the current lockfile
+    Added(PackageId),
+
+    /// Package which is only present in the previous lockfile
+    Removed(PackageId),
+
+    /// Package whose version increased
+    Upgraded(VersionChange),
+
+    /// Package whose version decreased
+    Downgraded(VersionChange),
+
+    /// Package whose version stayed the same but whose source changed
+    SourceChanged(SourceChange),
+}
+
+impl Change {
+    /// Get the name of the package which changed
+    pub fn name(&self) -> &Name {
+        match self {
+            Change::Added(id) | Change::Removed(id) => &id.name,
+            Change::Upgraded(change) | Change::Downgraded(change) => &change.name,
+            Change::SourceChanged(change) => &change.name,
+        }
+    }
+}
+
+/// Group the packages of a lockfile by name, sorted by version
+fn by_name(lockfile: &Lockfile) -> Map<&Name, Vec<&Package>> {
+    let mut packages = Map::<_, Vec<_>>::new();
+
+    for package in &lockfile.packages {
+        packages.entry(&package.name).or_default().push(package);
+    }
+
+    for versions in packages.values_mut() {
+        versions.sort_by(|a, b| (&a.version, &a.source).cmp(&(&b.version, &b.source)));
+    }
+
+    packages
+}
+
+/// Do both packages have the same version and source?
+fn same(a: &Package, b: &Package) -> bool {
+    a.version == b.version && a.source == b.source
+}
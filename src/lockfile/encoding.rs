@@ -4,18 +4,25 @@
 //! the V1 vs V2 formats and ensure the end-user is supplied a consistent
 //! representation regardless of which version is in use.
 
-use super::{Lockfile, ResolveVersion};
+use super::{Lockfile, Newline, ResolveVersion, SerializeOptions};
 use crate::{
-    metadata, Checksum, Dependency, Error, ErrorKind, Metadata, Name, Package, Patch, SourceId,
+    metadata::{self, MetadataExt},
+    Checksum, Dependency, Error, ErrorKind, Metadata, Name, Package, PackageId, Patch, SourceId,
     Version,
 };
 use serde::{de, ser, Deserialize, Serialize};
 use std::{
     convert::{TryFrom, TryInto},
-    fmt,
+    fmt, io,
     str::FromStr,
 };
 
+/// Comment header Cargo places at the top of all `Cargo.lock` files
+const GENERATED_HEADER: &str = "\
+# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+";
+
 impl<'de> Deserialize<'de> for Lockfile {
     fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let raw_lockfile = EncodableLockfile::deserialize(deserializer)?;
@@ -33,8 +40,12 @@ impl Serialize for Lockfile {
 /// Serialization-oriented equivalent to [`Lockfile`]
 #[derive(Debug, Deserialize, Serialize)]
 pub(super) struct EncodableLockfile {
+    /// Explicit format version (`ResolveVersion::V3` and newer)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) version: Option<u32>,
+
     /// Packages in the lockfile
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_packages")]
     pub(super) package: Vec<EncodablePackage>,
 
     /// Legacy root package (preserved for compatibility)
@@ -45,30 +56,431 @@ pub(super) struct EncodableLockfile {
     pub(super) metadata: Metadata,
 
     /// Patch section
-    #[serde(default, skip_serializing_if = "Patch::is_empty")]
-    pub(super) patch: Patch,
+    #[serde(default, skip_serializing_if = "EncodablePatch::is_empty")]
+    pub(super) patch: EncodablePatch,
+
+    /// Leading comment lines (not part of the TOML data model)
+    #[serde(skip)]
+    pub(super) preamble: Option<String>,
+}
+
+/// Serialization-oriented equivalent to [`Patch`]
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(super) struct EncodablePatch {
+    /// Unused patches
+    #[serde(default)]
+    pub(super) unused: Vec<EncodablePackage>,
+}
+
+impl EncodableLockfile {
+    /// Is there nothing at all in this lockfile, not even a `version` field?
+    fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.package.is_empty()
+            && self.root.is_none()
+            && self.metadata.is_empty()
+            && self.patch.is_empty()
+    }
+}
+
+impl EncodablePatch {
+    /// Is the `[patch]` section empty?
+    fn is_empty(&self) -> bool {
+        self.unused.is_empty()
+    }
+}
+
+/// Deserialize `[[package]]` entries, ensuring errors caused by invalid
+/// versions name the offending package
+fn deserialize_packages<'de, D: de::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<EncodablePackage>, D::Error> {
+    Vec::<toml::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|value| decode_package(value).map_err(de::Error::custom))
+        .collect()
+}
+
+/// Decode a `[[package]]` table
+pub(super) fn decode_package(value: toml::Value) -> Result<EncodablePackage, String> {
+    let name = value.get("name").and_then(toml::Value::as_str);
+    let version = value.get("version").and_then(toml::Value::as_str);
+
+    if let (Some(name), Some(version)) = (name, version) {
+        if let Err(e) = Version::parse(version) {
+            return Err(format!(
+                "invalid version `{}` for package `{}`: {}",
+                version, name, e
+            ));
+        }
+    }
+
+    value.try_into().map_err(|e| e.to_string())
 }
 
 impl EncodableLockfile {
+    /// Parse a lockfile from the given TOML string.
+    ///
+    /// Errors in `[[package]]` tables are reported by `toml` at the start
+    /// of the array rather than of the offending table, so they're located
+    /// by scanning for the table's header instead.
+    pub(super) fn parse(toml_string: &str) -> Result<Self, Error> {
+        let e = match toml::from_str(toml_string) {
+            Ok(raw_lockfile) => return Ok(raw_lockfile),
+            Err(e) => e,
+        };
+
+        let packages = match toml_string.parse::<toml::Value>() {
+            Ok(toml::Value::Table(mut table)) => {
+                if is_manifest(&table) {
+                    return Err(not_a_lockfile());
+                }
+
+                table.remove("package")
+            }
+            _ => None,
+        };
+
+        if let Some(toml::Value::Array(packages)) = packages {
+            let header_lines = package_header_lines(toml_string);
+
+            for (index, package) in packages.into_iter().enumerate() {
+                let line = match header_lines.get(index) {
+                    Some(&line) => line,
+                    None => break,
+                };
+
+                if let Err(msg) = decode_package(package) {
+                    return Err(format_err!(
+                        ErrorKind::Parse,
+                        "{} for [[package]] #{} at line {} column 1",
+                        msg,
+                        index + 1,
+                        line
+                    )
+                    .with_line_col(Some((line, 1))));
+                }
+            }
+        }
+
+        Err(e.into())
+    }
+
+    /// Check whether the given TOML string (which was parsed successfully,
+    /// but contains no packages) is a `Cargo.toml` manifest, e.g. a virtual
+    /// manifest which only contains a `[workspace]` table.
+    pub(super) fn check_not_manifest(toml_string: &str) -> Result<(), Error> {
+        match toml_string.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) if is_manifest(&table) => Err(not_a_lockfile()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Extract the comment lines at the top of a serialized lockfile (along
+    /// with any blank lines between them), if any. Line endings are
+    /// normalized to `\n`, since the newline style is chosen at
+    /// serialization time (see `SerializeOptions::newline`).
+    ///
+    /// Cargo's standard "@generated" header is returned as `None`.
+    pub(super) fn parse_preamble(toml_string: &str) -> Option<String> {
+        let mut preamble = String::new();
+
+        for line in toml_string.lines() {
+            if line.starts_with('#') {
+                preamble.push_str(line);
+                preamble.push('\n');
+            } else if !line.trim().is_empty() {
+                break;
+            } else if !preamble.is_empty() {
+                preamble.push('\n');
+            }
+        }
+
+        // Blank lines after the last comment are part of the body
+        let len = preamble.trim_end_matches('\n').len();
+
+        if len == 0 {
+            return None;
+        }
+
+        preamble.truncate(len + 1);
+
+        // Cargo's own header is what gets written for `None`, so parsing it
+        // back as `None` keeps constructed lockfiles round-trippable
+        if preamble == GENERATED_HEADER {
+            None
+        } else {
+            Some(preamble)
+        }
+    }
+
     /// Attempt to find a checksum for a package in a V1 lockfile
     pub fn find_checksum(&self, package: &Package) -> Option<Checksum> {
-        for (key, value) in &self.metadata {
-            if let Ok(dep) = key.checksum_dependency() {
-                if dep.name == package.name && dep.version == package.version {
-                    return value.checksum().ok();
+        self.metadata
+            .checksums()
+            .find(|(dep, _)| dep.name == package.name && dep.version == package.version)
+            .map(|(_, checksum)| checksum)
+    }
+}
+
+impl EncodableLockfile {
+    /// Write this lockfile using exactly the same formatting as Cargo.
+    ///
+    /// Modeled after `serialize_resolve` in Cargo's `ops/lockfile.rs`. The
+    /// output is streamed to `w` one table at a time.
+    pub(super) fn write_pretty(
+        &self,
+        version: ResolveVersion,
+        options: &SerializeOptions,
+        w: &mut impl io::Write,
+    ) -> io::Result<()> {
+        if options.bom {
+            w.write_all("\u{feff}".as_bytes())?;
+        }
+
+        let mut w = LineWriter::new(w, options.newline, options.compact);
+        let mut out = String::from(self.preamble.as_deref().unwrap_or(GENERATED_HEADER));
+
+        if let Some(explicit) = self.version {
+            out.push_str(&format!("version = {}\n\n", explicit));
+        }
+
+        if let Some(root) = &self.root {
+            out.push_str("[root]\n");
+            root.emit(&mut out);
+        }
+
+        w.push(&mut out)?;
+
+        let mut packages = self.package.iter().collect::<Vec<_>>();
+        packages.sort_by(|a, b| {
+            (&a.name, &a.version, &a.source).cmp(&(&b.name, &b.version, &b.source))
+        });
+
+        for package in packages {
+            out.push_str("[[package]]\n");
+            package.emit(&mut out);
+            w.push(&mut out)?;
+        }
+
+        for unused in &self.patch.unused {
+            out.push_str("[[patch.unused]]\n");
+            unused.emit(&mut out);
+            w.push(&mut out)?;
+        }
+
+        if !self.metadata.is_empty() {
+            out.push_str("[metadata]\n");
+
+            for (key, value) in &self.metadata {
+                let key = key.as_ref();
+
+                if is_bare_key(key) {
+                    out.push_str(key);
+                } else {
+                    push_quoted(&mut out, key);
                 }
+
+                out.push_str(" = ");
+                push_quoted(&mut out, value.as_ref());
+                out.push('\n');
+                w.push(&mut out)?;
             }
         }
 
-        None
+        // Cargo trims trailing blank lines for all formats after V1
+        w.finish(version >= ResolveVersion::V2)
     }
 }
 
+/// Writer for chunks of serialized output, which converts line endings and
+/// holds back trailing newlines so trailing blank lines can be trimmed
+struct LineWriter<'w, W: io::Write> {
+    /// Underlying writer
+    w: &'w mut W,
+
+    /// Line endings to use
+    newline: Newline,
+
+    /// Omit blank lines between chunks?
+    compact: bool,
+
+    /// Number of newlines at the end of the output which haven't been
+    /// written yet
+    pending: usize,
+}
+
+impl<'w, W: io::Write> LineWriter<'w, W> {
+    /// Create a new `LineWriter` for the given writer
+    fn new(w: &'w mut W, newline: Newline, compact: bool) -> Self {
+        Self {
+            w,
+            newline,
+            compact,
+            pending: 0,
+        }
+    }
+
+    /// Write the given chunk of output, clearing it for reuse
+    fn push(&mut self, chunk: &mut String) -> io::Result<()> {
+        let body = chunk.trim_end_matches('\n');
+        let trailing = chunk.len() - body.len();
+
+        if !body.is_empty() {
+            let pending = if self.compact {
+                self.pending.min(1)
+            } else {
+                self.pending
+            };
+
+            self.write_newlines(pending)?;
+            self.pending = 0;
+
+            // Chunks can contain blank lines too, e.g. after the header
+            let compacted;
+            let body = if self.compact && body.contains("\n\n") {
+                compacted = body
+                    .split('\n')
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                &compacted
+            } else {
+                body
+            };
+
+            // Newlines within values are always escaped, so every newline in
+            // the output is a line ending
+            if self.newline == Newline::Lf {
+                self.w.write_all(body.as_bytes())?;
+            } else {
+                let body = body.replace('\n', self.newline.as_str());
+                self.w.write_all(body.as_bytes())?;
+            }
+        }
+
+        self.pending += trailing;
+        chunk.clear();
+        Ok(())
+    }
+
+    /// Write the held back newlines at the end of the output, collapsing
+    /// trailing blank lines into a single line ending if `trim` is set
+    fn finish(mut self, trim: bool) -> io::Result<()> {
+        let pending = if trim || self.compact {
+            self.pending.min(1)
+        } else {
+            self.pending
+        };
+
+        self.write_newlines(pending)
+    }
+
+    /// Write the given number of line endings
+    fn write_newlines(&mut self, n: usize) -> io::Result<()> {
+        for _ in 0..n {
+            self.w.write_all(self.newline.as_str().as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Does the given TOML table look like a `Cargo.toml` manifest?
+fn is_manifest(table: &toml::value::Table) -> bool {
+    // Lockfiles only ever have arrays of `[[package]]` tables
+    matches!(table.get("package"), Some(toml::Value::Table(_)))
+        || [
+            "workspace",
+            "dependencies",
+            "dev-dependencies",
+            "build-dependencies",
+        ]
+        .iter()
+        .any(|key| table.contains_key(*key))
+}
+
+/// Get the error for trying to parse a manifest as a lockfile
+fn not_a_lockfile() -> Error {
+    format_err!(
+        ErrorKind::NotALockfile,
+        "input is a Cargo.toml manifest rather than a Cargo.lock file"
+    )
+}
+
+/// Find the line numbers (starting at 1) of all `[[package]]` table headers,
+/// in order
+fn package_header_lines(toml_string: &str) -> Vec<usize> {
+    toml_string
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_package_header(line))
+        .map(|(n, _)| n + 1)
+        .collect()
+}
+
+/// Is the given line a `[[package]]` table header?
+pub(super) fn is_package_header(line: &str) -> bool {
+    let header = line.split('#').next().unwrap_or_default();
+    header.split_whitespace().collect::<String>() == "[[package]]"
+}
+
+/// Emit a `key = "value"` line in Cargo's format
+pub(super) fn emit_field(out: &mut String, key: &str, value: &impl fmt::Display) {
+    out.push_str(key);
+    out.push_str(" = ");
+    push_quoted(out, &value.to_string());
+    out.push('\n');
+}
+
+/// Push a TOML basic string (i.e. double quoted, with escapes)
+pub(super) fn push_quoted(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Can the given string be used as a TOML key without quoting?
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 impl TryFrom<EncodableLockfile> for Lockfile {
     type Error = Error;
 
     fn try_from(raw_lockfile: EncodableLockfile) -> Result<Lockfile, Error> {
-        let version = ResolveVersion::detect(&raw_lockfile.package, &raw_lockfile.metadata)?;
+        // An explicit `version` field takes precedence over autodetection
+        let version = match raw_lockfile.version {
+            Some(explicit) => explicit.to_string().parse()?,
+            None => ResolveVersion::detect(&raw_lockfile.package, &raw_lockfile.metadata)?,
+        };
+
+        // Lockfiles without any packages are valid (e.g. `version = 3` on
+        // its own), but input without any content at all was likely truncated
+        if raw_lockfile.is_empty() {
+            fail!(
+                ErrorKind::MissingPackages,
+                "no [[package]] entries found in lockfile"
+            );
+        }
+
         let mut packages = Vec::with_capacity(raw_lockfile.package.len());
 
         for raw_package in &raw_lockfile.package {
@@ -76,17 +488,30 @@ impl TryFrom<EncodableLockfile> for Lockfile {
                 // In the V1 format, all dependencies are fully qualified with
                 // their versions, but their checksums are stored in metadata.
                 ResolveVersion::V1 => {
-                    let mut pkg = Package::try_from(raw_package)?;
+                    let mut pkg = raw_package.resolve(&raw_lockfile.package)?;
                     pkg.checksum = raw_lockfile.find_checksum(&pkg);
                     pkg
                 }
 
                 // In the V2 format, we may need to look up dependency versions
                 // from the other packages listed in the lockfile
-                ResolveVersion::V2 => raw_package.resolve(&raw_lockfile.package)?,
+                ResolveVersion::V2 | ResolveVersion::V3 | ResolveVersion::V4 => {
+                    raw_package.resolve(&raw_lockfile.package)?
+                }
             });
         }
 
+        let mut ids = packages.iter().map(PackageId::from).collect::<Vec<_>>();
+        ids.sort();
+
+        if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            fail!(
+                ErrorKind::DuplicatePackage,
+                "package defined more than once: {}",
+                pair[0]
+            );
+        }
+
         Ok(Lockfile {
             version,
             packages,
@@ -96,7 +521,15 @@ impl TryFrom<EncodableLockfile> for Lockfile {
                 .map(|root| root.try_into())
                 .transpose()?,
             metadata: raw_lockfile.metadata,
-            patch: raw_lockfile.patch,
+            patch: Patch {
+                unused: raw_lockfile
+                    .patch
+                    .unused
+                    .iter()
+                    .map(|unused| unused.try_into())
+                    .collect::<Result<_, _>>()?,
+            },
+            preamble: raw_lockfile.preamble,
         })
     }
 }
@@ -115,28 +548,77 @@ impl From<&Lockfile> for EncodableLockfile {
                 // packages and add it to metadata
                 ResolveVersion::V1 => {
                     if let Some(checksum) = raw_pkg.checksum.take() {
-                        let value = checksum.to_string().parse::<metadata::Value>().unwrap();
-                        metadata.insert(checksum_key, value);
+                        metadata.insert(checksum_key, (&checksum).into());
                     }
                 }
 
                 // In the V2 format, we need to remove the version/source from
                 // unambiguous dependencies, and remove checksums from the
                 // metadata table if present
-                ResolveVersion::V2 => {
+                ResolveVersion::V2 | ResolveVersion::V3 | ResolveVersion::V4 => {
                     raw_pkg.v2_deps(&lockfile.packages);
                     metadata.remove(&checksum_key);
                 }
             }
 
+            // Prior to V3, an explicit `master` branch is encoded the same
+            // way as the default branch
+            if lockfile.version < ResolveVersion::V3 {
+                raw_pkg.master_as_default_branch();
+            }
+
+            // As of V4, git references are percent-encoded
+            if lockfile.version >= ResolveVersion::V4 {
+                raw_pkg.url_encode_git_references();
+            }
+
             packages.push(raw_pkg);
         }
 
         EncodableLockfile {
+            version: lockfile.version.explicit(),
             package: packages,
-            root: lockfile.root.as_ref().map(|root| root.into()),
+            root: lockfile.root.as_ref().map(|root| {
+                let mut raw_root = EncodablePackage::from(root);
+
+                if lockfile.version < ResolveVersion::V3 {
+                    raw_root.master_as_default_branch();
+                }
+
+                if lockfile.version >= ResolveVersion::V4 {
+                    raw_root.url_encode_git_references();
+                }
+
+                raw_root
+            }),
             metadata,
-            patch: lockfile.patch.clone(),
+            patch: EncodablePatch {
+                unused: lockfile
+                    .patch
+                    .unused
+                    .iter()
+                    .map(|unused| {
+                        let mut raw_unused = EncodablePackage::from(unused);
+
+                        // Cargo only records checksums of unused patches
+                        // inline, which the V1 format doesn't support
+                        if lockfile.version == ResolveVersion::V1 {
+                            raw_unused.checksum = None;
+                        }
+
+                        if lockfile.version < ResolveVersion::V3 {
+                            raw_unused.master_as_default_branch();
+                        }
+
+                        if lockfile.version >= ResolveVersion::V4 {
+                            raw_unused.url_encode_git_references();
+                        }
+
+                        raw_unused
+                    })
+                    .collect(),
+            },
+            preamble: lockfile.preamble.clone(),
         }
     }
 }
@@ -194,6 +676,82 @@ impl EncodablePackage {
             dependency.v2(packages);
         }
     }
+
+    /// Encode git sources on an explicit `master` branch as the default
+    /// branch, as is done prior to `ResolveVersion::V3`
+    fn master_as_default_branch(&mut self) {
+        let dependencies = self.dependencies.iter_mut().chain(self.replace.as_mut());
+
+        for source in dependencies
+            .filter_map(|dep| dep.source.as_mut())
+            .chain(self.source.as_mut())
+        {
+            *source = source.with_master_as_default_branch();
+        }
+    }
+
+    /// Percent-encode the references of git sources, as is done as of
+    /// `ResolveVersion::V4`
+    fn url_encode_git_references(&mut self) {
+        let dependencies = self.dependencies.iter_mut().chain(self.replace.as_mut());
+
+        for source in dependencies
+            .filter_map(|dep| dep.source.as_mut())
+            .chain(self.source.as_mut())
+        {
+            *source = source.with_url_encoded_git_reference();
+        }
+    }
+
+    /// Emit the fields of this package in Cargo's format (see
+    /// `emit_package` in Cargo's `ops/lockfile.rs`)
+    fn emit(&self, out: &mut String) {
+        emit_field(out, "name", &self.name);
+        emit_field(out, "version", &self.version);
+
+        if let Some(source) = &self.source {
+            emit_field(out, "source", source);
+        }
+
+        if let Some(checksum) = &self.checksum {
+            emit_field(out, "checksum", checksum);
+        }
+
+        if let Some(replace) = self
+            .replace
+            .as_ref()
+            .filter(|_| self.dependencies.is_empty())
+        {
+            emit_field(out, "replace", replace);
+        } else if !self.dependencies.is_empty() {
+            // Cargo sorts dependencies by their (abbreviated) string fields
+            let mut dependencies = self
+                .dependencies
+                .iter()
+                .map(|dep| {
+                    let key = (
+                        dep.name.to_string(),
+                        dep.version.as_ref().map(ToString::to_string),
+                        dep.source.as_ref().map(ToString::to_string),
+                    );
+                    (key, dep)
+                })
+                .collect::<Vec<_>>();
+
+            dependencies.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push_str("dependencies = [\n");
+
+            for (_, dependency) in dependencies {
+                out.push(' ');
+                push_quoted(out, &dependency.to_string());
+                out.push_str(",\n");
+            }
+
+            out.push_str("]\n");
+        }
+
+        out.push('\n');
+    }
 }
 
 /// Note: this only works for `ResolveVersion::V1` dependencies.
@@ -239,54 +797,91 @@ impl EncodableDependency {
     /// Resolve this dependency, which in the V2 format may be abbreviated to
     /// prevent merge conflicts
     pub fn resolve(&self, packages: &[EncodablePackage]) -> Result<Dependency, Error> {
-        let mut version = None;
-        let mut source = None;
+        let mut matching = packages
+            .iter()
+            .filter(|pkg| self.matches(pkg))
+            .collect::<Vec<_>>();
 
-        if let Some(v) = &self.version {
-            version = Some(v.clone());
-            source = self.source.clone();
-        } else {
-            for pkg in packages {
-                if pkg.name == self.name {
-                    if version.is_some() {
-                        fail!(ErrorKind::Parse, "ambiguous dependency: {}", self.name);
-                    }
+        // Packages without a source (i.e. path dependencies) are referenced
+        // without one even when the name and version are ambiguous
+        let ambiguous = matching.len() > 1;
 
-                    version = Some(pkg.version.clone());
-                    source = pkg.source.clone();
-                }
-            }
-        };
+        if ambiguous && self.source.is_none() {
+            matching.retain(|pkg| pkg.source.is_none());
+        }
 
-        if version.is_none() {
-            fail!(
-                ErrorKind::Parse,
+        if matching.len() > 1 || (ambiguous && matching.is_empty()) {
+            fail!(ErrorKind::Parse, "ambiguous dependency: {}", self);
+        }
+
+        if let Some(pkg) = matching.first() {
+            return Ok(Dependency {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                source: pkg.source.clone(),
+            });
+        }
+
+        // Fall back to the (fully qualified) dependency itself
+        match &self.version {
+            Some(version) => Ok(Dependency {
+                name: self.name.clone(),
+                version: version.clone(),
+                source: self.source.clone(),
+            }),
+            None => fail!(
+                ErrorKind::DanglingDependency,
                 "couldn't resolve dependency: {}",
                 self.name
-            );
+            ),
         }
-
-        Ok(Dependency {
-            name: self.name.clone(),
-            version: version.unwrap(),
-            source,
-        })
     }
 
-    /// Prepare `ResolveVersion::V2` dependencies by removing ones which are unambiguous
-    pub fn v2(&mut self, packages: &[Package]) {
-        let mut matching = vec![];
+    /// Could this (possibly abbreviated) dependency refer to the given package?
+    ///
+    /// Sources of dependencies don't include the "precise" part of the
+    /// package's source (e.g. the git commit hash), so it isn't compared.
+    fn matches(&self, package: &EncodablePackage) -> bool {
+        if package.name != self.name {
+            return false;
+        }
 
-        for package in packages {
-            if package.name == self.name {
-                matching.push(package);
+        if let Some(version) = &self.version {
+            if package.version != *version {
+                return false;
             }
         }
 
-        // TODO(tarcieri): better handle other cases?
-        if matching.len() == 1 {
-            self.version = None;
+        match (&self.source, &package.source) {
+            (Some(source), Some(pkg_source)) => {
+                source.with_precise(None) == pkg_source.with_precise(None)
+            }
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Prepare `ResolveVersion::V2` dependencies by removing the version
+    /// and/or source when they are unambiguous, following the same rules as
+    /// Cargo: the source is omitted when only one package has this name and
+    /// version, and the version is omitted when only one package has this name
+    pub fn v2(&mut self, packages: &[Package]) {
+        let matching = packages
+            .iter()
+            .filter(|package| package.name == self.name)
+            .collect::<Vec<_>>();
+
+        let same_version = matching
+            .iter()
+            .filter(|package| Some(&package.version) == self.version.as_ref())
+            .count();
+
+        if same_version == 1 {
             self.source = None;
+
+            if matching.len() == 1 {
+                self.version = None;
+            }
         }
     }
 }
@@ -361,7 +956,10 @@ impl From<&Dependency> for EncodableDependency {
         EncodableDependency {
             name: package.name.clone(),
             version: Some(package.version.clone()),
-            source: package.source.clone(),
+            source: package
+                .source
+                .as_ref()
+                .map(|source| source.with_precise(None)),
         }
     }
 }
@@ -0,0 +1,138 @@
+//! Aggregate metrics about lockfiles
+
+use super::Lockfile;
+use crate::{package::Name, Dependency};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+/// Metrics about the size and shape of a lockfile's dependency graph (see
+/// [`Lockfile::metrics`])
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct LockfileMetrics {
+    /// Total number of packages
+    pub packages: usize,
+
+    /// Number of distinct package names
+    pub names: usize,
+
+    /// Number of package names with more than one package, e.g. multiple
+    /// versions of the same crate
+    pub duplicates: usize,
+
+    /// Length (in dependency edges) of the longest chain of dependencies
+    /// starting at a root package. Dependency cycles aren't followed, so
+    /// packages in (or only reachable through) a cycle don't count.
+    pub max_depth: usize,
+
+    /// Number of root packages, i.e. ones no other package depends on
+    pub roots: usize,
+
+    /// Number of leaf packages, i.e. ones without any dependencies
+    pub leaves: usize,
+
+    /// Number of packages by kind of source
+    pub sources: SourceCounts,
+}
+
+/// Number of packages by kind of source
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct SourceCounts {
+    /// Local packages, i.e. ones without a source (e.g. workspace members)
+    /// or from a filesystem path
+    pub path: usize,
+
+    /// Packages from a registry accessed via git
+    pub registry: usize,
+
+    /// Packages from a registry accessed via the sparse protocol
+    pub sparse: usize,
+
+    /// Packages from a git repository
+    pub git: usize,
+
+    /// Packages from any other kind of source (i.e. local registries and
+    /// directory sources)
+    pub other: usize,
+}
+
+impl LockfileMetrics {
+    /// Compute the metrics for the given lockfile in a single pass over its
+    /// dependency graph, i.e. in `O(V + E)` (expected) time.
+    pub fn new(lockfile: &Lockfile) -> Self {
+        let packages = &lockfile.packages;
+        let mut metrics = Self {
+            packages: packages.len(),
+            ..Self::default()
+        };
+
+        let mut names = HashMap::<&Name, usize>::new();
+        let mut indexes = HashMap::new();
+
+        for (index, package) in packages.iter().enumerate() {
+            *names.entry(&package.name).or_default() += 1;
+            indexes.insert(Dependency::from(package), index);
+
+            let sources = &mut metrics.sources;
+
+            match &package.source {
+                None => sources.path += 1,
+                Some(source) if source.is_path() => sources.path += 1,
+                Some(source) if source.is_sparse() => sources.sparse += 1,
+                Some(source) if source.is_remote_registry() => sources.registry += 1,
+                Some(source) if source.is_git() => sources.git += 1,
+                Some(_) => sources.other += 1,
+            }
+        }
+
+        metrics.names = names.len();
+        metrics.duplicates = names.values().filter(|&&count| count > 1).count();
+
+        // Dependency edges, by the index of the depending package
+        let mut edges = vec![vec![]; packages.len()];
+        let mut in_degree = vec![0; packages.len()];
+
+        for (index, package) in packages.iter().enumerate() {
+            // Only count each dependency once, even if it's listed twice
+            let mut dependencies = package
+                .dependencies
+                .iter()
+                .filter_map(|dependency| indexes.get(dependency).copied())
+                .collect::<Vec<_>>();
+
+            dependencies.sort_unstable();
+            dependencies.dedup();
+
+            if dependencies.is_empty() {
+                metrics.leaves += 1;
+            }
+
+            for dependency in dependencies {
+                edges[index].push(dependency);
+                in_degree[dependency] += 1;
+            }
+        }
+
+        // Longest path from a root, visiting packages in topological order
+        let mut queue = (0..packages.len())
+            .filter(|&index| in_degree[index] == 0)
+            .collect::<VecDeque<_>>();
+
+        metrics.roots = queue.len();
+        let mut depths = vec![0; packages.len()];
+
+        while let Some(index) = queue.pop_front() {
+            metrics.max_depth = metrics.max_depth.max(depths[index]);
+
+            for &dependency in &edges[index] {
+                depths[dependency] = depths[dependency].max(depths[index] + 1);
+                in_degree[dependency] -= 1;
+
+                if in_degree[dependency] == 0 {
+                    queue.push_back(dependency);
+                }
+            }
+        }
+
+        metrics
+    }
+}
@@ -0,0 +1,121 @@
+//! Options shared by the SBOM exports
+
+use crate::package::{Package, SourceId};
+use std::time::SystemTime;
+use url::{form_urlencoded, Url};
+
+/// Options for generating software bills of materials (SBOMs) from
+/// lockfiles using `Lockfile::to_cyclonedx_with_options` or
+/// `Lockfile::to_spdx_with_options`.
+///
+/// The `cyclonedx` or `spdx` Cargo feature must be enabled to use this.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SbomOptions {
+    /// URL of the index to treat as crates.io (without a `registry+` or
+    /// `sparse+` prefix), e.g. a mirror which all crates are resolved from.
+    ///
+    /// Packages from this index (accessed via either protocol) get plain
+    /// `pkg:cargo/<name>@<version>` package URLs, while packages from every
+    /// other registry (including crates.io itself, if overridden) have their
+    /// index recorded in a `repository_url` qualifier. Defaults to crates.io.
+    pub crates_io_index: Option<Url>,
+
+    /// Creation time recorded in SPDX documents.
+    ///
+    /// Defaults to the time given by the `SOURCE_DATE_EPOCH` environment
+    /// variable (in seconds since the Unix epoch, for reproducible builds)
+    /// if set, or else the current time.
+    pub created: Option<SystemTime>,
+}
+
+impl SbomOptions {
+    /// Is the given source the index treated as crates.io?
+    fn is_crates_io(&self, source: &SourceId) -> bool {
+        match &self.crates_io_index {
+            Some(index) => {
+                source.is_remote_registry()
+                    && source.url().as_str().trim_end_matches('/')
+                        == index.as_str().trim_end_matches('/')
+            }
+            None => source.is_default_registry(),
+        }
+    }
+}
+
+/// Get the [package URL] for the given package
+///
+/// [package URL]: https://github.com/package-url/purl-spec
+pub(super) fn purl(package: &Package, options: &SbomOptions) -> String {
+    let mut purl = format!("pkg:cargo/{}@{}", package.name, package.version);
+    let qualifier = package
+        .source
+        .as_ref()
+        .and_then(|source| source_qualifier(source, options));
+
+    if let Some((key, value)) = qualifier {
+        let value = form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>();
+        purl.push_str(&format!("?{}={}", key, value));
+    }
+
+    purl
+}
+
+/// Get the package URL qualifier recording the given source, if it isn't
+/// crates.io (which is implied by the `cargo` package URL type)
+fn source_qualifier(source: &SourceId, options: &SbomOptions) -> Option<(&'static str, String)> {
+    if options.is_crates_io(source) {
+        None
+    } else if source.is_git() {
+        let url = match source.precise() {
+            Some(rev) => format!("git+{}@{}", source.url(), rev),
+            None => format!("git+{}", source.url()),
+        };
+
+        Some(("vcs_url", url))
+    } else if source.is_remote_registry() {
+        Some(("repository_url", source.url().to_string()))
+    } else {
+        Some(("download_url", source.url().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{purl, SbomOptions};
+    use crate::Package;
+
+    fn package(source: &str) -> Package {
+        Package {
+            name: "example".parse().unwrap(),
+            version: "1.0.0".parse().unwrap(),
+            source: Some(source.parse().unwrap()),
+            checksum: None,
+            dependencies: vec![],
+            replace: None,
+        }
+    }
+
+    #[test]
+    fn crates_io_index_override() {
+        let crates_io = package("registry+https://github.com/rust-lang/crates.io-index");
+        let mirror = package("sparse+https://mirror.example.com/index/");
+        let default = SbomOptions::default();
+
+        assert_eq!(purl(&crates_io, &default), "pkg:cargo/example@1.0.0");
+        assert_eq!(
+            purl(&mirror, &default),
+            "pkg:cargo/example@1.0.0?repository_url=https%3A%2F%2Fmirror.example.com%2Findex%2F"
+        );
+
+        let options = SbomOptions {
+            crates_io_index: Some("https://mirror.example.com/index".parse().unwrap()),
+            ..SbomOptions::default()
+        };
+
+        assert_eq!(purl(&mirror, &options), "pkg:cargo/example@1.0.0");
+        assert_eq!(
+            purl(&crates_io, &options),
+            "pkg:cargo/example@1.0.0?repository_url=https%3A%2F%2Fgithub.com%2Frust-lang%2Fcrates.io-index"
+        );
+    }
+}
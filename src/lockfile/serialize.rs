@@ -0,0 +1,48 @@
+//! Lockfile serialization options
+
+/// Options for serializing lockfiles via [`Lockfile::to_writer_with_options`].
+///
+/// The defaults match Cargo, i.e. LF line endings, no byte order mark, and
+/// blank lines between tables.
+///
+/// [`Lockfile::to_writer_with_options`]: crate::Lockfile::to_writer_with_options
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SerializeOptions {
+    /// Line endings to use
+    pub newline: Newline,
+
+    /// Begin the output with a UTF-8 byte order mark, as expected by some
+    /// Windows toolchains
+    pub bom: bool,
+
+    /// Omit all blank lines (e.g. between `[[package]]` tables), for a more
+    /// compact output when embedding lockfiles elsewhere. This differs from
+    /// Cargo's formatting, but is parsed the same.
+    pub compact: bool,
+}
+
+/// Newline styles
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Newline {
+    /// Unix-style line endings (`\n`), as used by Cargo
+    Lf,
+
+    /// Windows-style line endings (`\r\n`)
+    CrLf,
+}
+
+impl Default for Newline {
+    fn default() -> Self {
+        Newline::Lf
+    }
+}
+
+impl Newline {
+    /// Get the line ending as a string
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
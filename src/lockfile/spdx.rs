@@ -0,0 +1,269 @@
+//! SPDX software bill of materials (SBOM) export
+
+use super::{
+    sbom::{self, SbomOptions},
+    Lockfile,
+};
+use crate::package::{Package, SourceKind};
+use std::{
+    collections::HashSet,
+    env,
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Version of the SPDX specification documents are generated for
+const SPEC_VERSION: &str = "SPDX-2.3";
+
+/// Value used for fields whose contents aren't known
+const NOASSERTION: &str = "NOASSERTION";
+
+impl Lockfile {
+    /// Generate a minimal [SPDX] 2.3 SBOM (in the tag-value format) listing
+    /// the packages in this lockfile.
+    ///
+    /// Each package is described by its name, version, download location,
+    /// checksum (as `PackageChecksum: SHA256`, if any), and [package URL]
+    /// (as an `ExternalRef`, qualified with its source unless it's from
+    /// crates.io, as for [`Lockfile::to_cyclonedx`]). Packages from
+    /// registries use the registry's URL as their download location, while
+    /// git, path, and local sources are recorded as `NOASSERTION`. Packages
+    /// are sorted by name, version, and source, and identified by `SPDXID`s
+    /// derived from their name and version, and the document's namespace is
+    /// derived from [`Lockfile::content_hash`], so the output only depends
+    /// on its creation time (see [`SbomOptions::created`]).
+    ///
+    /// The `spdx` Cargo feature must be enabled to use this.
+    ///
+    /// [SPDX]: https://spdx.dev/
+    /// [package URL]: https://github.com/package-url/purl-spec
+    pub fn to_spdx(&self) -> String {
+        self.to_spdx_with_options(&SbomOptions::default())
+    }
+
+    /// Generate an [SPDX] SBOM like [`Lockfile::to_spdx`], using the given
+    /// options (e.g. to treat a mirror as crates.io).
+    ///
+    /// [SPDX]: https://spdx.dev/
+    pub fn to_spdx_with_options(&self, options: &SbomOptions) -> String {
+        let mut packages = self.packages.iter().collect::<Vec<_>>();
+        packages.sort();
+
+        let mut out = String::new();
+        writeln!(out, "SPDXVersion: {}", SPEC_VERSION).unwrap();
+        writeln!(out, "DataLicense: CC0-1.0").unwrap();
+        writeln!(out, "SPDXID: SPDXRef-DOCUMENT").unwrap();
+        writeln!(out, "DocumentName: Cargo.lock").unwrap();
+        writeln!(
+            out,
+            "DocumentNamespace: https://spdx.org/spdxdocs/cargo-lock-{}",
+            self.content_hash()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "Creator: Tool: cargo-lock-{}",
+            env!("CARGO_PKG_VERSION")
+        )
+        .unwrap();
+        writeln!(out, "Created: {}", timestamp(creation_time(options))).unwrap();
+
+        let mut ids = HashSet::new();
+
+        for package in packages {
+            let id = spdx_id(package, &mut ids);
+            push_package(&mut out, package, &id, options);
+        }
+
+        out
+    }
+}
+
+/// Push the tag-value block describing the given package
+fn push_package(out: &mut String, package: &Package, id: &str, options: &SbomOptions) {
+    writeln!(out).unwrap();
+    writeln!(out, "PackageName: {}", package.name).unwrap();
+    writeln!(out, "SPDXID: {}", id).unwrap();
+    writeln!(out, "PackageVersion: {}", package.version).unwrap();
+    writeln!(
+        out,
+        "PackageDownloadLocation: {}",
+        download_location(package)
+    )
+    .unwrap();
+    writeln!(out, "FilesAnalyzed: false").unwrap();
+
+    if let Some(checksum) = &package.checksum {
+        writeln!(out, "PackageChecksum: SHA256: {:x}", checksum).unwrap();
+    }
+
+    writeln!(
+        out,
+        "ExternalRef: PACKAGE-MANAGER purl {}",
+        sbom::purl(package, options)
+    )
+    .unwrap();
+}
+
+/// Get the `SPDXID` of the given package, which is derived from its name
+/// and version (disambiguated using a numeric suffix if already taken)
+fn spdx_id(package: &Package, ids: &mut HashSet<String>) -> String {
+    // Identifiers may only contain letters, numbers, `.`, and `-`
+    let base = format!("SPDXRef-Package-{}-{}", package.name, package.version)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    let mut id = base.clone();
+    let mut n = 1;
+
+    while !ids.insert(id.clone()) {
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+
+    id
+}
+
+/// Get the creation time of the document, i.e. the one given in the options,
+/// by `SOURCE_DATE_EPOCH`, or else the current time
+fn creation_time(options: &SbomOptions) -> SystemTime {
+    if let Some(created) = options.created {
+        return created;
+    }
+
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or_else(SystemTime::now)
+}
+
+/// Format the given time as an SPDX timestamp (`YYYY-MM-DDThh:mm:ssZ`, in
+/// UTC), clamping times before the Unix epoch to it
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // Convert days since the epoch to a civil date in the proleptic
+    // Gregorian calendar, counting years from March so that leap days come
+    // last (see http://howardhinnant.github.io/date_algorithms.html)
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Get the download location of the given package, if it's from a registry
+fn download_location(package: &Package) -> String {
+    match &package.source {
+        Some(source) => match source.kind() {
+            SourceKind::CratesIo | SourceKind::AlternateRegistry => source.url().to_string(),
+            SourceKind::Git | SourceKind::Path | SourceKind::Local => NOASSERTION.to_owned(),
+        },
+        None => NOASSERTION.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timestamp, Lockfile, SbomOptions};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn spdx_packages() {
+        let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        let options = SbomOptions {
+            created: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            ..SbomOptions::default()
+        };
+        let sbom = lockfile.to_spdx_with_options(&options);
+
+        assert!(sbom.starts_with("SPDXVersion: SPDX-2.3\nDataLicense: CC0-1.0\n"));
+        assert!(sbom.contains("\nCreated: 2023-11-14T22:13:20Z\n"));
+        assert!(sbom.contains(
+            "PackageName: app\n\
+             SPDXID: SPDXRef-Package-app-0.1.0\n\
+             PackageVersion: 0.1.0\n\
+             PackageDownloadLocation: NOASSERTION\n"
+        ));
+        assert!(sbom.contains(
+            "PackageName: master-branch\n\
+             SPDXID: SPDXRef-Package-master-branch-0.3.0\n\
+             PackageVersion: 0.3.0\n\
+             PackageDownloadLocation: NOASSERTION\n"
+        ));
+        assert!(sbom.contains(
+            "PackageDownloadLocation: https://github.com/rust-lang/crates.io-index\n\
+             FilesAnalyzed: false\n\
+             PackageChecksum: SHA256: 414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449\n\
+             ExternalRef: PACKAGE-MANAGER purl pkg:cargo/"
+        ));
+        assert!(sbom.contains("ExternalRef: PACKAGE-MANAGER purl pkg:cargo/app@0.1.0\n"));
+
+        // The output is independent of the order of packages
+        let mut reversed = lockfile.clone();
+        reversed.packages.reverse();
+        assert_eq!(reversed.to_spdx_with_options(&options), sbom);
+    }
+
+    #[test]
+    fn spdx_timestamps() {
+        let at = |secs| timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_399), "2000-02-28T23:59:59Z");
+        assert_eq!(at(951_868_799), "2000-02-29T23:59:59Z");
+        assert_eq!(at(4_107_542_400), "2100-03-01T00:00:00Z");
+        assert_eq!(
+            timestamp(UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn spdx_ids() {
+        let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        let mut package = lockfile.packages[0].clone();
+        package.name = "with_underscore".parse().unwrap();
+        package.version = "1.0.0+build".parse().unwrap();
+        package.source = None;
+        lockfile.packages.push(package.clone());
+        package.name = "with-underscore".parse().unwrap();
+        lockfile.packages.push(package);
+
+        let sbom = lockfile.to_spdx();
+        assert!(sbom.contains("SPDXID: SPDXRef-Package-with-underscore-1.0.0-build\n"));
+        assert!(sbom.contains("SPDXID: SPDXRef-Package-with-underscore-1.0.0-build-2\n"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Incremental parsing of `[[package]]` entries
+
+use super::{encoding, Lockfile};
+use crate::{
+    error::{Error, ErrorKind},
+    package::Package,
+};
+use std::{
+    convert::TryFrom,
+    io::{self, BufRead},
+    mem,
+};
+
+impl Lockfile {
+    /// Parse the `[[package]]` entries of a lockfile one at a time while
+    /// reading it, e.g. to scan the package names of a very large lockfile
+    /// without loading all of it into memory at once.
+    ///
+    /// Resolving the dependencies of a package can require all of the other
+    /// packages (since V2 lockfiles abbreviate unambiguous dependencies), so
+    /// the `dependencies` of the packages produced are always empty, and
+    /// V1-style checksums (which are stored in a `[metadata]` table at the
+    /// end of the file) aren't filled in. Use [`Lockfile::load`] if they're
+    /// needed.
+    ///
+    /// Invalid entries produce an [`ErrorKind::Parse`] error, after which the
+    /// following entries are still parsed. Failing to read from `reader`
+    /// produces an [`ErrorKind::Io`] error and ends the iteration.
+    pub fn stream_packages<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Package, Error>> {
+        PackageStream {
+            lines: reader.lines(),
+            line: 0,
+            count: 0,
+            start: None,
+            section: String::new(),
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the packages of a lockfile which is being read
+struct PackageStream<R> {
+    /// Lines of the lockfile
+    lines: io::Lines<R>,
+
+    /// Number of lines read so far
+    line: usize,
+
+    /// Number of `[[package]]` entries found so far
+    count: usize,
+
+    /// Line of the header of the `[[package]]` entry being read (if any)
+    start: Option<usize>,
+
+    /// Contents of the `[[package]]` entry being read (without its header)
+    section: String,
+
+    /// Was the end of the input (or a read error) reached?
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for PackageStream<R> {
+    type Item = Result<Package, Error>;
+
+    fn next(&mut self) -> Option<Result<Package, Error>> {
+        while !self.done {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+                None => {
+                    self.done = true;
+                    break;
+                }
+            };
+
+            self.line += 1;
+
+            // Any other table header ends the entry being read
+            let next_start = if encoding::is_package_header(&line) {
+                Some(self.line)
+            } else if line.trim_start().starts_with('[') {
+                None
+            } else {
+                if self.start.is_some() {
+                    self.section.push_str(&line);
+                    self.section.push('\n');
+                }
+
+                continue;
+            };
+
+            if let Some(start) = mem::replace(&mut self.start, next_start) {
+                return Some(self.decode(start));
+            }
+        }
+
+        self.start.take().map(|start| self.decode(start))
+    }
+}
+
+impl<R> PackageStream<R> {
+    /// Decode the entry read so far, whose header is on the given line
+    fn decode(&mut self, start: usize) -> Result<Package, Error> {
+        self.count += 1;
+        let section = mem::take(&mut self.section);
+
+        let value = section.parse::<toml::Value>().map_err(|e| {
+            // Make the location reported by `toml` relative to the lockfile
+            let (line, col) = e
+                .line_col()
+                .map_or((start, 1), |(line, col)| (start + line + 1, col + 1));
+            let msg = e.to_string();
+            let msg = msg.rsplit_once(" at line ").map_or(&*msg, |(msg, _)| msg);
+
+            format_err!(ErrorKind::Parse, "{} at line {} column {}", msg, line, col)
+                .with_line_col(Some((line, col)))
+        })?;
+
+        let mut raw_package = encoding::decode_package(value).map_err(|msg| {
+            format_err!(
+                ErrorKind::Parse,
+                "{} for [[package]] #{} at line {} column 1",
+                msg,
+                self.count,
+                start
+            )
+            .with_line_col(Some((start, 1)))
+        })?;
+
+        raw_package.dependencies.clear();
+        Package::try_from(&raw_package)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lockfile;
+    use crate::error::ErrorKind;
+    use std::{fs, io::BufReader};
+
+    #[test]
+    fn stream_packages() {
+        let path = "tests/support/Cargo.lock.v3-example";
+        let lockfile = Lockfile::load(path).unwrap();
+        let file = BufReader::new(fs::File::open(path).unwrap());
+
+        let packages = Lockfile::stream_packages(file)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(packages.len(), lockfile.packages.len());
+
+        for (streamed, package) in packages.iter().zip(&lockfile.packages) {
+            assert_eq!(streamed.name, package.name);
+            assert_eq!(streamed.version, package.version);
+            assert_eq!(streamed.source, package.source);
+            assert_eq!(streamed.checksum, package.checksum);
+            assert!(streamed.dependencies.is_empty());
+        }
+    }
+
+    #[test]
+    fn stream_invalid_packages() {
+        let toml = "version = 3\n\n\
+                    [[package]]\nname = \"a\"\nversion = \"1.0.x\"\n\n\
+                    [[package]]\nname = \"b\"\nversion = = \"1.0.0\"\n\n\
+                    [[package]]\nname = \"c\"\nversion = \"1.0.0\"\n\n\
+                    [metadata]\n";
+
+        let results = Lockfile::stream_packages(toml.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+
+        let err = results[0].as_ref().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert_eq!(err.line_col(), Some((3, 1)));
+        assert!(err.msg().ends_with("for [[package]] #1 at line 3 column 1"));
+
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(err.line_col().map(|(line, _)| line), Some(9));
+
+        assert_eq!(results[2].as_ref().unwrap().name.as_str(), "c");
+    }
+}
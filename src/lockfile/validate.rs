@@ -0,0 +1,120 @@
+//! Validating the internal consistency of lockfiles
+
+use super::{Lockfile, ResolveVersion};
+use crate::{package::PackageId, Dependency};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+/// Problems found when validating a lockfile (see [`Lockfile::validate`])
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    /// The same package (i.e. name, version, and source) is listed more
+    /// than once
+    DuplicatePackage(PackageId),
+
+    /// A dependency refers to a package which isn't in the lockfile
+    DanglingDependency {
+        /// Package with the dependency
+        package: PackageId,
+
+        /// Dependency which doesn't refer to any package
+        dependency: Dependency,
+    },
+
+    /// A package from a remote registry has no checksum, even though the
+    /// resolve version requires them
+    MissingChecksum(PackageId),
+
+    /// The declared resolve version disagrees with what the lockfile's
+    /// contents indicate (see [`Lockfile::resolve_version_mismatch`])
+    ResolveVersionMismatch {
+        /// Resolve version declared by the lockfile
+        declared: ResolveVersion,
+
+        /// Resolve version detected from the lockfile's contents
+        detected: ResolveVersion,
+    },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::DuplicatePackage(id) => {
+                write!(f, "package defined more than once: {}", id)
+            }
+            ValidationError::DanglingDependency {
+                package,
+                dependency,
+            } => write!(
+                f,
+                "{} depends on a package missing from the lockfile: {}",
+                package, dependency
+            ),
+            ValidationError::MissingChecksum(id) => write!(f, "missing checksum: {}", id),
+            ValidationError::ResolveVersionMismatch { declared, detected } => write!(
+                f,
+                "lockfile declares resolve version {} but its contents indicate version {}",
+                declared, detected
+            ),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Check that this lockfile is internally consistent, e.g. after editing
+    /// it, returning all problems found (rather than just the first).
+    ///
+    /// Lockfiles are checked for:
+    ///
+    /// - packages with the same name, version, and source
+    /// - dependencies which don't refer to any package
+    /// - packages from remote registries without checksums (V2 and newer
+    ///   lockfiles only, as V1 ones may predate checksums)
+    /// - a declared resolve version which disagrees with the lockfile's
+    ///   contents (see [`Lockfile::resolve_version_mismatch`])
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        let mut ids = HashSet::new();
+
+        for package in &self.packages {
+            let id = PackageId::from(package);
+
+            if !ids.insert(id.clone()) {
+                errors.push(ValidationError::DuplicatePackage(id));
+            }
+        }
+
+        for (package, dependency) in self.dangling_dependencies() {
+            errors.push(ValidationError::DanglingDependency {
+                package: package.into(),
+                dependency: dependency.clone(),
+            });
+        }
+
+        if self.version != ResolveVersion::V1 {
+            for package in &self.packages {
+                let is_registry = package
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| source.is_remote_registry());
+
+                if is_registry && package.checksum.is_none() {
+                    errors.push(ValidationError::MissingChecksum(package.into()));
+                }
+            }
+        }
+
+        if let Some((declared, detected)) = self.resolve_version_mismatch() {
+            errors.push(ValidationError::ResolveVersionMismatch { declared, detected });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
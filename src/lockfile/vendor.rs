@@ -0,0 +1,139 @@
+//! Source replacement configuration for vendored packages
+
+use super::{encoding, Lockfile};
+use crate::package::{source::GitReference, SourceId};
+use std::collections::BTreeMap;
+
+/// Name of the source which vendored packages are redirected to
+const VENDORED_SOURCES: &str = "vendored-sources";
+
+/// Directory `cargo vendor` vendors packages into by default
+const VENDOR_DIR: &str = "vendor";
+
+impl Lockfile {
+    /// Generate the `.cargo/config.toml` source replacement configuration
+    /// which `cargo vendor` would print for this lockfile, redirecting all
+    /// registries and git repositories packages are from to a vendored
+    /// `vendor` directory, without having to run it.
+    ///
+    /// crates.io (regardless of the protocol used to access it) is replaced
+    /// via `[source.crates-io]`, and every other registry via a
+    /// `[source."<url>"]` entry. Git repositories get a
+    /// `[source."git+<url>"]` entry with the `branch`, `tag`, or `rev` their
+    /// packages were referred to with. The commit each one is locked to
+    /// isn't included: like Cargo, sources are matched by that reference,
+    /// and the vendored copy is of the locked commit anyway. Path and local
+    /// sources aren't vendored, so they don't need replacing.
+    pub fn vendor_config(&self) -> String {
+        let mut crates_io = false;
+        let mut sources = BTreeMap::new();
+
+        for source in self.packages.iter().filter_map(|pkg| pkg.source.as_ref()) {
+            if source.is_default_registry() {
+                crates_io = true;
+            } else if source.is_git() {
+                let source = source.with_precise(None);
+                sources.insert(source.to_string(), source);
+            } else if source.is_remote_registry() {
+                sources.insert(registry_url(source), source.clone());
+            }
+        }
+
+        let mut out = String::new();
+
+        if crates_io {
+            out.push_str("[source.crates-io]\n");
+            encoding::emit_field(&mut out, "replace-with", &VENDORED_SOURCES);
+            out.push('\n');
+        }
+
+        for (key, source) in &sources {
+            out.push_str("[source.");
+            encoding::push_quoted(&mut out, key);
+            out.push_str("]\n");
+
+            if source.is_git() {
+                encoding::emit_field(&mut out, "git", source.url());
+
+                match source.git_reference() {
+                    Some(GitReference::Branch(branch)) => {
+                        encoding::emit_field(&mut out, "branch", branch)
+                    }
+                    Some(GitReference::Tag(tag)) => encoding::emit_field(&mut out, "tag", tag),
+                    Some(GitReference::Rev(rev)) => encoding::emit_field(&mut out, "rev", rev),
+                    Some(GitReference::DefaultBranch) | None => (),
+                }
+            } else {
+                encoding::emit_field(&mut out, "registry", key);
+            }
+
+            encoding::emit_field(&mut out, "replace-with", &VENDORED_SOURCES);
+            out.push('\n');
+        }
+
+        out.push_str("[source.");
+        out.push_str(VENDORED_SOURCES);
+        out.push_str("]\n");
+        encoding::emit_field(&mut out, "directory", &VENDOR_DIR);
+        out
+    }
+}
+
+/// Get the URL of the given registry as used in source configuration, i.e.
+/// with a `sparse+` prefix if it's accessed via the sparse protocol
+fn registry_url(source: &SourceId) -> String {
+    if source.is_sparse() {
+        format!("sparse+{}", source.url())
+    } else {
+        source.url().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lockfile;
+
+    #[test]
+    fn vendor_config() {
+        let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        let mut package = lockfile.packages.last().unwrap().clone();
+        package.name = "private".parse().unwrap();
+        package.source = Some(
+            "sparse+https://registry.example.com/index/"
+                .parse()
+                .unwrap(),
+        );
+        lockfile.packages.push(package);
+
+        assert_eq!(
+            lockfile.vendor_config(),
+            "[source.crates-io]\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.\"git+https://github.com/example/default-branch\"]\n\
+             git = \"https://github.com/example/default-branch\"\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.\"git+https://github.com/example/master-branch?branch=master\"]\n\
+             git = \"https://github.com/example/master-branch\"\n\
+             branch = \"master\"\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.\"sparse+https://registry.example.com/index/\"]\n\
+             registry = \"sparse+https://registry.example.com/index/\"\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.vendored-sources]\n\
+             directory = \"vendor\"\n"
+        );
+    }
+
+    #[test]
+    fn vendor_config_without_sources() {
+        let lockfile = "version = 3\n".parse::<Lockfile>().unwrap();
+        assert_eq!(
+            lockfile.vendor_config(),
+            "[source.vendored-sources]\ndirectory = \"vendor\"\n"
+        );
+    }
+}
@@ -1,12 +1,21 @@
 //! Lockfile versions
 
-use super::encoding::EncodablePackage;
+use super::{
+    encoding::{self, EncodablePackage},
+    Lockfile,
+};
 use crate::{
     error::{Error, ErrorKind},
-    metadata::Metadata,
+    metadata::{Metadata, MetadataExt},
+    package::PackageId,
+    Dependency,
 };
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader},
+    str::FromStr,
+};
 
 /// Lockfile versions
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize)]
@@ -20,10 +29,40 @@ pub enum ResolveVersion {
     ///
     /// <https://github.com/rust-lang/cargo/pull/7070>
     V2,
+
+    /// Like `V2`, but declares itself with a top-level `version = 3` field
+    /// and distinguishes git dependencies on an explicit `master` branch
+    /// from ones on the repository's default branch. For more information,
+    /// see:
+    ///
+    /// <https://github.com/rust-lang/cargo/pull/8522>
+    V3,
+
+    /// Like `V3`, but declares itself with `version = 4` and percent-encodes
+    /// the branch, tag, or revision of git sources (e.g. `?branch=a%2Fb`).
+    /// It's the default for new lockfiles as of Cargo 1.83. For more
+    /// information, see:
+    ///
+    /// <https://github.com/rust-lang/cargo/pull/12852>
+    V4,
 }
 
 impl ResolveVersion {
-    /// Autodetect the version of a lockfile from the packages
+    /// Get the value of the top-level `version` field for this format, if
+    /// it has one
+    pub(super) fn explicit(self) -> Option<u32> {
+        match self {
+            ResolveVersion::V1 | ResolveVersion::V2 => None,
+            ResolveVersion::V3 => Some(3),
+            ResolveVersion::V4 => Some(4),
+        }
+    }
+
+    /// Autodetect the version of a lockfile from the packages.
+    ///
+    /// Only lockfiles without an explicit `version` field are autodetected,
+    /// so this never returns `V3` or newer: the field is authoritative for
+    /// those formats.
     pub(super) fn detect(
         packages: &[EncodablePackage],
         metadata: &Metadata,
@@ -34,6 +73,56 @@ impl ResolveVersion {
         // V2: look for `checksum` fields in `[package]`
         let is_v2 = packages.iter().any(|package| package.checksum.is_some());
 
+        Self::from_checksums(is_v1, is_v2)
+    }
+
+    /// Determine the resolve version of a lockfile while reading it, without
+    /// deserializing its packages, e.g. to cheaply check which format a
+    /// lockfile is in.
+    ///
+    /// Reading stops at the explicit top-level `version` field if there is
+    /// one. Otherwise the version is detected from the checksums found in
+    /// the rest of the lockfile, the same way as when it's parsed (so input
+    /// which isn't a lockfile at all is assumed to be `V2`).
+    pub fn peek(reader: impl io::Read) -> Result<Self, Error> {
+        let mut section = Section::Top;
+        let mut is_v1 = false;
+        let mut is_v2 = false;
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.trim_start_matches('\u{feff}').trim();
+
+            if line.starts_with('[') {
+                section = if encoding::is_package_header(line) {
+                    Section::Package
+                } else if line.split('#').next().unwrap_or_default().trim() == "[metadata]" {
+                    Section::Metadata
+                } else {
+                    Section::Other
+                };
+
+                continue;
+            }
+
+            match section {
+                Section::Top => {
+                    if let Some(version) = field_value(line, "version") {
+                        return version.parse();
+                    }
+                }
+                Section::Package => is_v2 |= field_value(line, "checksum").is_some(),
+                Section::Metadata => is_v1 |= line.starts_with("\"checksum "),
+                Section::Other => (),
+            }
+        }
+
+        Self::from_checksums(is_v1, is_v2)
+    }
+
+    /// Get the version of a lockfile without an explicit `version` field,
+    /// given whether it has checksums in `[metadata]` and in its packages
+    fn from_checksums(is_v1: bool, is_v2: bool) -> Result<Self, Error> {
         if is_v1 && is_v2 {
             fail!(ErrorKind::Parse, "malformed lockfile: contains checksums in both [[package]] and [[metadata]] sections");
         }
@@ -47,6 +136,28 @@ impl ResolveVersion {
     }
 }
 
+/// Sections of a lockfile distinguished by [`ResolveVersion::peek`]
+enum Section {
+    /// Top-level keys before any table
+    Top,
+
+    /// A `[[package]]` entry
+    Package,
+
+    /// The `[metadata]` table
+    Metadata,
+
+    /// Any other table
+    Other,
+}
+
+/// Get the value of the given field if it's set on the given line (with any
+/// trailing comment removed)
+fn field_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let value = line.strip_prefix(key)?.trim_start().strip_prefix('=')?;
+    Some(value.split('#').next().unwrap_or_default().trim())
+}
+
 /// V2 format is now the default.
 ///
 ///See: <https://github.com/rust-lang/cargo/pull/7579>
@@ -63,11 +174,170 @@ impl FromStr for ResolveVersion {
         match s {
             "1" => Ok(ResolveVersion::V1),
             "2" => Ok(ResolveVersion::V2),
+            "3" => Ok(ResolveVersion::V3),
+            "4" => Ok(ResolveVersion::V4),
             _ => fail!(
-                ErrorKind::Parse,
+                ErrorKind::UnsupportedVersion,
                 "invalid Cargo.lock format version: `{}`",
                 s
             ),
         }
     }
 }
+
+impl fmt::Display for ResolveVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = match self {
+            ResolveVersion::V1 => 1,
+            ResolveVersion::V2 => 2,
+            ResolveVersion::V3 => 3,
+            ResolveVersion::V4 => 4,
+        };
+
+        write!(f, "{}", n)
+    }
+}
+
+/// Error changing the resolve version of a lockfile to an older one which
+/// can't represent all of its contents (see [`Lockfile::set_resolve_version`])
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DowngradeError {
+    /// Current resolve version of the lockfile
+    pub from: ResolveVersion,
+
+    /// Resolve version the lockfile couldn't be changed to
+    pub to: ResolveVersion,
+
+    /// Information which would have been lost
+    pub losses: Vec<DowngradeLoss>,
+}
+
+impl fmt::Display for DowngradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "changing resolve version {} to {} would lose information",
+            self.from, self.to
+        )?;
+
+        for (i, loss) in self.losses.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ":" } else { ";" }, loss)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for DowngradeError {}
+
+/// Information which can't be represented in an older resolve version (see
+/// [`DowngradeError`])
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DowngradeLoss {
+    /// A package is from a git source referring to an explicit `master`
+    /// branch, which versions prior to V3 represent the same way as the
+    /// repository's default branch
+    ExplicitMasterBranch(PackageId),
+
+    /// An unused patch has a checksum, which V1 can't represent
+    UnusedPatchChecksum(PackageId),
+
+    /// A package is from a git source whose branch, tag, or revision is only
+    /// represented faithfully when percent-encoded, which versions prior to
+    /// V4 don't do
+    UrlEncodedGitReference(PackageId),
+}
+
+impl fmt::Display for DowngradeLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DowngradeLoss::ExplicitMasterBranch(id) => write!(
+                f,
+                "explicit `master` branch of {} would become the default branch",
+                id
+            ),
+            DowngradeLoss::UnusedPatchChecksum(id) => {
+                write!(f, "checksum of unused patch {} would be dropped", id)
+            }
+            DowngradeLoss::UrlEncodedGitReference(id) => write!(
+                f,
+                "git reference of {} would no longer be percent-encoded",
+                id
+            ),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Change the resolve version of this lockfile (i.e. the format it's
+    /// serialized in), unless that would lose information because the new
+    /// version is older and can't represent all of the lockfile's contents.
+    ///
+    /// In that case, the lockfile is left unchanged and all information
+    /// which would be lost is returned. To change the version regardless,
+    /// set [`Lockfile::version`] directly.
+    ///
+    /// Checksums are migrated between the formats: when changing from V1,
+    /// the `checksum <name> <version> (<source>)` entries of `[metadata]`
+    /// are moved onto their packages (see
+    /// [`Lockfile::inline_metadata_checksums`]), and when changing to V1,
+    /// an entry is added for every package with a checksum (which keeps it
+    /// as well, like the packages of parsed V1 lockfiles).
+    pub fn set_resolve_version(&mut self, version: ResolveVersion) -> Result<(), DowngradeError> {
+        let mut losses = vec![];
+
+        let packages = self
+            .packages
+            .iter()
+            .chain(self.root.as_ref())
+            .chain(&self.patch.unused);
+
+        for package in packages {
+            let source = match &package.source {
+                Some(source) => source,
+                None => continue,
+            };
+
+            if version < ResolveVersion::V3 && source.is_explicit_master_branch() {
+                losses.push(DowngradeLoss::ExplicitMasterBranch(package.into()));
+            }
+
+            if self.version >= ResolveVersion::V4
+                && version < ResolveVersion::V4
+                && source.needs_url_encoded_git_reference()
+            {
+                losses.push(DowngradeLoss::UrlEncodedGitReference(package.into()));
+            }
+        }
+
+        if version == ResolveVersion::V1 {
+            for unused in &self.patch.unused {
+                if unused.checksum.is_some() {
+                    losses.push(DowngradeLoss::UnusedPatchChecksum(unused.into()));
+                }
+            }
+        }
+
+        if losses.is_empty() {
+            if self.version == ResolveVersion::V1 && version != ResolveVersion::V1 {
+                self.inline_metadata_checksums();
+            } else if self.version != ResolveVersion::V1 && version == ResolveVersion::V1 {
+                for package in &self.packages {
+                    if let Some(checksum) = &package.checksum {
+                        self.metadata
+                            .set_checksum(&Dependency::from(package), checksum);
+                    }
+                }
+            }
+
+            self.version = version;
+            Ok(())
+        } else {
+            Err(DowngradeError {
+                from: self.version,
+                to: version,
+                losses,
+            })
+        }
+    }
+}
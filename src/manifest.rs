@@ -0,0 +1,301 @@
+//! Minimal `Cargo.toml` manifest support
+//!
+//! `Cargo.lock` files don't record why a package was included in the
+//! resolve, i.e. whether it's a normal, dev, or build dependency. This
+//! module parses just enough of a package's `Cargo.toml` to recover the
+//! kinds of its direct dependencies.
+
+use crate::{
+    error::{Error, ErrorKind},
+    package::Name,
+    Map,
+};
+use semver::VersionReq;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Parsed `Cargo.toml` manifest
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Manifest {
+    /// Name of the package defined by this manifest (`None` for virtual
+    /// workspace manifests)
+    pub package: Option<Name>,
+
+    /// Direct dependencies of the package, of all kinds and for all targets
+    pub dependencies: Vec<Dependency>,
+
+    /// Overrides from `[patch]` tables, keyed by the registry name (e.g.
+    /// `crates-io`) or URL they patch. Their `kind` is always
+    /// [`DependencyKind::Normal`].
+    pub patch: Map<String, Vec<Dependency>>,
+}
+
+impl Manifest {
+    /// Load a manifest from a `Cargo.toml` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(s) => s.parse(),
+            Err(e) => fail!(
+                ErrorKind::Io,
+                "couldn't open {}: {}",
+                path.as_ref().display(),
+                e
+            ),
+        }
+    }
+
+    /// Iterate over the `[patch]` entries of this manifest, as pairs of the
+    /// registry (or URL) they patch and the overriding dependencies
+    pub fn patches(&self) -> impl Iterator<Item = (&str, &[Dependency])> {
+        self.patch
+            .iter()
+            .map(|(registry, deps)| (registry.as_str(), deps.as_slice()))
+    }
+
+    /// Iterate over the dependencies of the given kind
+    pub fn dependencies_of_kind(&self, kind: DependencyKind) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.iter().filter(move |dep| dep.kind == kind)
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = Error;
+
+    fn from_str(toml_string: &str) -> Result<Self, Error> {
+        let raw_manifest: EncodableManifest = toml::from_str(toml_string)?;
+        let mut dependencies = vec![];
+
+        let targets = raw_manifest
+            .target
+            .iter()
+            .map(|(target, deps)| (Some(target), deps));
+
+        for (target, deps) in Some((None, &raw_manifest.deps)).into_iter().chain(targets) {
+            for (kind, table) in &[
+                (DependencyKind::Normal, &deps.dependencies),
+                (DependencyKind::Dev, &deps.dev_dependencies),
+                (DependencyKind::Build, &deps.build_dependencies),
+            ] {
+                for (name, value) in table.iter() {
+                    dependencies.push(Dependency::from_toml(name, value, *kind, target)?);
+                }
+            }
+        }
+
+        let mut patch = Map::new();
+
+        for (registry, table) in &raw_manifest.patch {
+            let deps = table
+                .iter()
+                .map(|(name, value)| {
+                    Dependency::from_toml(name, value, DependencyKind::Normal, None)
+                })
+                .collect::<Result<_, _>>()?;
+
+            patch.insert(registry.clone(), deps);
+        }
+
+        Ok(Manifest {
+            package: raw_manifest.package.map(|package| package.name),
+            dependencies,
+            patch,
+        })
+    }
+}
+
+/// Kinds of dependencies
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum DependencyKind {
+    /// Normal dependencies (`[dependencies]`)
+    Normal,
+
+    /// Development dependencies (`[dev-dependencies]`)
+    Dev,
+
+    /// Build dependencies (`[build-dependencies]`)
+    Build,
+}
+
+/// Dependency declared in a `Cargo.toml` manifest
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dependency {
+    /// Name the dependency is declared (and imported) under
+    pub name: Name,
+
+    /// Name of the package this dependency refers to, if it's been renamed
+    /// using the `package` key
+    pub package: Option<Name>,
+
+    /// Version requirement of the dependency (if any)
+    pub req: Option<VersionReq>,
+
+    /// Kind of the dependency
+    pub kind: DependencyKind,
+
+    /// Platform this dependency is specific to (i.e. the key of a
+    /// `[target.<platform>]` table)
+    pub target: Option<String>,
+
+    /// Is this dependency inherited from the workspace (`workspace = true`)?
+    pub workspace: bool,
+
+    /// Path of a path dependency, relative to the manifest's directory
+    pub path: Option<PathBuf>,
+}
+
+impl Dependency {
+    /// Get the name of the package this dependency refers to, accounting
+    /// for renames
+    pub fn package_name(&self) -> &Name {
+        self.package.as_ref().unwrap_or(&self.name)
+    }
+
+    /// Parse a dependency from its `Cargo.toml` entry
+    fn from_toml(
+        name: &str,
+        value: &toml::Value,
+        kind: DependencyKind,
+        target: Option<&String>,
+    ) -> Result<Self, Error> {
+        let (req, package, workspace, path) = match value {
+            toml::Value::String(req) => (Some(req.as_str()), None, false, None),
+            toml::Value::Table(table) => (
+                table.get("version").and_then(toml::Value::as_str),
+                table.get("package").and_then(toml::Value::as_str),
+                table
+                    .get("workspace")
+                    .and_then(toml::Value::as_bool)
+                    .unwrap_or(false),
+                table.get("path").and_then(toml::Value::as_str),
+            ),
+            _ => fail!(
+                ErrorKind::Parse,
+                "malformed dependency in manifest: {}",
+                name
+            ),
+        };
+
+        Ok(Dependency {
+            name: name.parse()?,
+            package: package.map(str::parse).transpose()?,
+            req: req.map(VersionReq::parse).transpose()?,
+            kind,
+            target: target.cloned(),
+            workspace,
+            path: path.map(PathBuf::from),
+        })
+    }
+}
+
+/// Reasons a manifest dependency isn't satisfied by a lockfile (see
+/// [`Lockfile::unsatisfied_dependencies`](crate::Lockfile::unsatisfied_dependencies))
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Unsatisfied {
+    /// No package in the lockfile has the dependency's name and a version
+    /// matching its requirement
+    Missing,
+
+    /// The dependency is inherited from the workspace (`workspace = true`),
+    /// whose manifest isn't read, so its requirement is unknown
+    WorkspaceInherited,
+}
+
+/// Serialization-oriented equivalent to [`Manifest`]
+#[derive(Debug, Deserialize)]
+struct EncodableManifest {
+    /// The `[package]` section
+    package: Option<EncodableManifestPackage>,
+
+    /// Platform-independent dependency tables
+    #[serde(flatten)]
+    deps: EncodableDependencies,
+
+    /// Platform-specific dependency tables
+    #[serde(default)]
+    target: Map<String, EncodableDependencies>,
+
+    /// Patch tables, by the registry they patch
+    #[serde(default)]
+    patch: Map<String, Map<String, toml::Value>>,
+}
+
+/// The `[package]` section of a manifest
+#[derive(Debug, Deserialize)]
+struct EncodableManifestPackage {
+    /// Package name
+    name: Name,
+}
+
+/// Dependency tables of a manifest (or of a `[target]` within it)
+#[derive(Debug, Deserialize)]
+struct EncodableDependencies {
+    /// The `[dependencies]` table
+    #[serde(default)]
+    dependencies: Map<String, toml::Value>,
+
+    /// The `[dev-dependencies]` table
+    #[serde(default, rename = "dev-dependencies", alias = "dev_dependencies")]
+    dev_dependencies: Map<String, toml::Value>,
+
+    /// The `[build-dependencies]` table
+    #[serde(default, rename = "build-dependencies", alias = "build_dependencies")]
+    build_dependencies: Map<String, toml::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DependencyKind, Manifest};
+
+    #[test]
+    fn parse_dependency_kinds() {
+        let manifest = r#"
+            [package]
+            name = "example"
+
+            [dependencies]
+            serde = "1"
+            json = { version = "1.0", package = "serde_json" }
+
+            [dev-dependencies]
+            tempfile = { workspace = true }
+            helper = { path = "../helper" }
+
+            [target.'cfg(unix)'.build-dependencies]
+            cc = "1.0"
+
+            [patch.crates-io]
+            serde = { git = "https://github.com/example/serde" }
+        "#
+        .parse::<Manifest>()
+        .unwrap();
+
+        assert_eq!(manifest.package.as_ref().unwrap().as_str(), "example");
+        assert_eq!(manifest.dependencies.len(), 5);
+
+        let json = &manifest.dependencies[0];
+        assert_eq!(json.name.as_str(), "json");
+        assert_eq!(json.package_name().as_str(), "serde_json");
+        assert_eq!(json.kind, DependencyKind::Normal);
+
+        let helper = &manifest.dependencies[2];
+        assert_eq!(helper.path.as_ref().unwrap().to_str(), Some("../helper"));
+
+        let tempfile = &manifest.dependencies[3];
+        assert_eq!(tempfile.kind, DependencyKind::Dev);
+        assert!(tempfile.workspace);
+        assert!(tempfile.req.is_none());
+
+        let cc = &manifest.dependencies[4];
+        assert_eq!(cc.kind, DependencyKind::Build);
+        assert_eq!(cc.target.as_ref().unwrap(), "cfg(unix)");
+
+        let patches = manifest.patches().collect::<Vec<_>>();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].0, "crates-io");
+        assert_eq!(patches[0].1[0].name.as_str(), "serde");
+    }
+}
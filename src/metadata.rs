@@ -18,6 +18,35 @@ const CHECKSUM_PREFIX: &str = "checksum ";
 /// Package metadata
 pub type Metadata = Map<Key, Value>;
 
+/// Typed access to the V1-style checksum entries of [`Metadata`], i.e.
+/// `checksum <name> <version> (<source>) = "<hash>"` keys. Other entries
+/// are left as-is.
+pub trait MetadataExt {
+    /// Iterate over the dependencies and checksums of all valid checksum
+    /// entries, skipping other (or malformed) entries
+    fn checksums(&self) -> Box<dyn Iterator<Item = (Dependency, Checksum)> + '_>;
+
+    /// Add a checksum entry for the given dependency, returning the value it
+    /// replaced (if any)
+    fn set_checksum(&mut self, dep: &Dependency, checksum: &Checksum) -> Option<Value>;
+}
+
+impl MetadataExt for Metadata {
+    fn checksums(&self) -> Box<dyn Iterator<Item = (Dependency, Checksum)> + '_> {
+        Box::new(self.iter().filter_map(|(key, value)| {
+            if !key.is_checksum() {
+                return None;
+            }
+
+            Some((key.checksum_dependency().ok()?, value.checksum().ok()?))
+        }))
+    }
+
+    fn set_checksum(&mut self, dep: &Dependency, checksum: &Checksum) -> Option<Value> {
+        self.insert(Key::for_checksum(dep), checksum.into())
+    }
+}
+
 /// Keys for the `[metadata]` table
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct Key(String);
@@ -120,6 +149,12 @@ impl FromStr for Value {
     }
 }
 
+impl From<&Checksum> for Value {
+    fn from(checksum: &Checksum) -> Value {
+        Value(checksum.to_string())
+    }
+}
+
 impl TryFrom<&Value> for Checksum {
     type Error = Error;
 
@@ -1,16 +1,32 @@
 //! Rust packages enumerated in `Cargo.lock`
 
 pub mod checksum;
+pub mod id;
 pub mod name;
 pub mod source;
+#[cfg(feature = "verify")]
+mod verify;
+pub mod version;
 
-pub use self::{checksum::Checksum, name::Name, source::SourceId};
+pub use self::{
+    checksum::Checksum,
+    id::PackageId,
+    name::Name,
+    source::{CratesIoProtocol, SourceId, SourceKind},
+};
 pub use semver::Version;
 
 use crate::dependency::Dependency;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Information about a Rust package (as sourced from `Cargo.lock`)
+///
+/// Packages are ordered by their name, version, and source, which is the
+/// canonical order Cargo writes `[[package]]` entries in (and the order of
+/// [`Lockfile::sort`](crate::Lockfile::sort)). The other fields only break
+/// ties between packages with the same ID, which can't both be present in a
+/// valid lockfile, so downstream code can rely on this ordering.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, PartialOrd, Ord, Serialize)]
 pub struct Package {
     /// Name of the package
@@ -32,3 +48,53 @@ pub struct Package {
     /// Replace directive
     pub replace: Option<Dependency>,
 }
+
+impl Package {
+    /// Is this package's source fully reproducible?
+    ///
+    /// Packages are pinned if they're from a registry (or other remote
+    /// source) and have a checksum, or from a git repository with a precise
+    /// commit. Packages from paths, or from git without a precise commit
+    /// (i.e. floating on a branch), are not.
+    pub fn is_pinned(&self) -> bool {
+        match &self.source {
+            None => false,
+            Some(source) if source.is_path() => false,
+            Some(source) if source.is_git() => source.precise().is_some(),
+            Some(_) => self.checksum.is_some(),
+        }
+    }
+}
+
+/// Multi-line rendering of all of a package's fields, one per line and in a
+/// fixed order (with dependencies sorted), so text diffs of two packages are
+/// easy to read. Absent fields are rendered as `none`.
+///
+/// See the [`Dependency`] `Display` impl for a terse single-line rendering.
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn optional(value: Option<&impl fmt::Display>) -> String {
+            value.map_or_else(|| "none".to_owned(), ToString::to_string)
+        }
+
+        writeln!(f, "name: {}", self.name)?;
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "source: {}", optional(self.source.as_ref()))?;
+        writeln!(f, "checksum: {}", optional(self.checksum.as_ref()))?;
+
+        if self.dependencies.is_empty() {
+            writeln!(f, "dependencies: none")?;
+        } else {
+            let mut dependencies = self.dependencies.iter().collect::<Vec<_>>();
+            dependencies.sort();
+
+            writeln!(f, "dependencies:")?;
+
+            for dependency in dependencies {
+                writeln!(f, "  - {}", dependency)?;
+            }
+        }
+
+        writeln!(f, "replace: {}", optional(self.replace.as_ref()))
+    }
+}
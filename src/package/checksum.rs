@@ -11,7 +11,43 @@ pub enum Checksum {
     Sha256([u8; 32]),
 }
 
+/// Hash algorithms used for package checksums
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum Algorithm {
+    /// SHA-256 (the only algorithm Cargo currently uses)
+    Sha256,
+}
+
+impl Algorithm {
+    /// Get the name of this algorithm
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl Checksum {
+    /// Get the hash algorithm of this checksum
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Checksum::Sha256(_) => Algorithm::Sha256,
+        }
+    }
+
+    /// Get the raw bytes of this checksum's digest
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Checksum::Sha256(digest) => digest,
+        }
+    }
+
     /// Is this checksum SHA-256?
     pub fn is_sha256(&self) -> bool {
         self.as_sha256().is_some()
@@ -35,6 +71,16 @@ impl FromStr for Checksum {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Error> {
+        // `from_str_radix` alone would also accept signs (e.g. `+f`), and
+        // slicing non-ASCII strings could split characters
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+            fail!(
+                ErrorKind::Parse,
+                "invalid checksum: expected 64 hex chars, got {:?}",
+                c
+            );
+        }
+
         if s.len() != 64 {
             fail!(
                 ErrorKind::Parse,
@@ -111,13 +157,20 @@ impl Serialize for Checksum {
 
 #[cfg(test)]
 mod tests {
-    use super::{Checksum, ErrorKind};
+    use super::{Algorithm, Checksum, ErrorKind};
 
     #[test]
     fn checksum_round_trip() {
         let checksum_str = "af6f3550d8dff9ef7dc34d384ac6f107e5d31c8f57d9f28e0081503f547ac8f5";
         let checksum = checksum_str.parse::<Checksum>().unwrap();
         assert_eq!(checksum_str, checksum.to_string());
+        assert_eq!(checksum.algorithm(), Algorithm::Sha256);
+        assert_eq!(checksum.as_bytes()[..2], [0xaf, 0x6f]);
+
+        // Uppercase hex is accepted, but always serialized as lowercase
+        let uppercase = checksum_str.to_uppercase().parse::<Checksum>().unwrap();
+        assert_eq!(uppercase, checksum);
+        assert_eq!(uppercase.to_string(), checksum_str);
     }
 
     #[test]
@@ -127,4 +180,18 @@ mod tests {
         let error = invalid_str.parse::<Checksum>().err().unwrap();
         assert_eq!(error.kind(), ErrorKind::Parse);
     }
+
+    #[test]
+    fn non_hex_checksum() {
+        let invalid = [
+            "+f6f3550d8dff9ef7dc34d384ac6f107e5d31c8f57d9f28e0081503f547ac8f5",
+            "af6f3550d8dff9ef7dc34d384ac6f107e5d31c8f57d9f28e0081503f547ac8fg",
+            "af6f3550d8dff9ef7dc34d384ac6f107e5d31c8f57d9f28e0081503f547ac8\u{e9}",
+        ];
+
+        for s in &invalid {
+            let error = s.parse::<Checksum>().unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::Parse, "{}", s);
+        }
+    }
 }
@@ -0,0 +1,61 @@
+//! Package identifiers
+
+use super::{Name, Package, SourceId, Version};
+use crate::dependency::Dependency;
+use serde::{ser, Serialize};
+use std::fmt;
+
+/// Identifier which uniquely identifies a package within a lockfile: its
+/// name, version, and source.
+///
+/// Ordered by name, then version, then source, and displayed in the same
+/// `name version (source)` form Cargo uses.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct PackageId {
+    /// Name of the package
+    pub name: Name,
+
+    /// Version of the package
+    pub version: Version,
+
+    /// Source identifier for the package
+    pub source: Option<SourceId>,
+}
+
+impl From<&Package> for PackageId {
+    fn from(pkg: &Package) -> PackageId {
+        Self {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            source: pkg.source.clone(),
+        }
+    }
+}
+
+impl From<&Dependency> for PackageId {
+    fn from(dep: &Dependency) -> PackageId {
+        Self {
+            name: dep.name.clone(),
+            version: dep.version.clone(),
+            source: dep.source.clone(),
+        }
+    }
+}
+
+impl fmt::Display for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", &self.name, &self.version)?;
+
+        if let Some(source) = &self.source {
+            write!(f, " ({})", source)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for PackageId {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
@@ -1,12 +1,16 @@
 //! Package names
 
 use crate::Error;
-use serde::{Deserialize, Serialize};
-use std::{fmt, str::FromStr};
+use serde::{de, ser, Deserialize, Serialize};
+use std::{fmt, str::FromStr, sync::Arc};
 
 /// Name of a Rust `[[package]]`
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize)]
-pub struct Name(String);
+///
+/// Names are cheap to clone: the dependencies of the packages in a parsed
+/// lockfile (and the nodes of dependency graphs) share the storage of the
+/// names of the packages they refer to.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Name(Arc<str>);
 
 impl Name {
     /// Get package name as an `&str`
@@ -35,3 +39,38 @@ impl FromStr for Name {
         Ok(Name(s.into()))
     }
 }
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Name {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_str().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Lockfile;
+    use std::sync::Arc;
+
+    #[test]
+    fn dependencies_share_names() {
+        let lockfile = "[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n\
+                        dependencies = [\"b\"]\n\n\
+                        [[package]]\nname = \"b\"\nversion = \"1.0.0\"\n"
+            .parse::<Lockfile>()
+            .unwrap();
+
+        let dependency = &lockfile.packages[0].dependencies[0];
+        assert_eq!(dependency.name, lockfile.packages[1].name);
+        assert!(Arc::ptr_eq(
+            &dependency.name.0,
+            &lockfile.packages[1].name.0
+        ));
+    }
+}
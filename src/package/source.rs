@@ -9,12 +9,19 @@
 
 use crate::error::{Error, ErrorKind};
 use serde::{de, ser, Deserialize, Serialize};
-use std::{fmt, path::Path, str::FromStr};
-use url::Url;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use url::{form_urlencoded, Url};
 
 /// Location of the crates.io index
 pub const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
 
+/// Location of the crates.io index when accessed via the sparse protocol
+pub const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io/";
+
 /// Unique identifier for a source of packages.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct SourceId {
@@ -22,7 +29,7 @@ pub struct SourceId {
     url: Url,
 
     /// The source kind.
-    kind: SourceKind,
+    kind: SourceType,
 
     /// For example, the exact Git revision of the specified branch for a Git Source.
     precise: Option<String>,
@@ -31,10 +38,42 @@ pub struct SourceId {
     name: Option<String>,
 }
 
+/// Classification of sources by where their code comes from (see
+/// [`SourceId::kind`])
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum SourceKind {
+    /// The crates.io registry, accessed via either the git or the sparse
+    /// protocol
+    CratesIo,
+
+    /// Any other remote registry
+    AlternateRegistry,
+
+    /// A git repository
+    Git,
+
+    /// A local path
+    Path,
+
+    /// A local registry or directory source, e.g. vendored crates
+    Local,
+}
+
+/// Protocol used to access the crates.io index (see
+/// [`SourceId::crates_io_protocol`])
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum CratesIoProtocol {
+    /// The git protocol, i.e. `registry+https://github.com/rust-lang/crates.io-index`
+    Git,
+
+    /// The sparse (HTTP) protocol, i.e. `sparse+https://index.crates.io/`
+    Sparse,
+}
+
 /// The possible kinds of code source. Along with `SourceIdInner`, this fully defines the
 /// source.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
-enum SourceKind {
+enum SourceType {
     /// A git repository.
     Git(GitReference),
 
@@ -44,6 +83,9 @@ enum SourceKind {
     /// A remote registry.
     Registry,
 
+    /// A remote registry accessed via the sparse (HTTP) protocol.
+    SparseRegistry,
+
     /// A local filesystem-based registry.
     LocalRegistry,
 
@@ -53,7 +95,7 @@ enum SourceKind {
 
 impl SourceId {
     /// Creates a `SourceId` object from the kind and URL.
-    fn new(kind: SourceKind, url: Url) -> Result<Self, Error> {
+    fn new(kind: SourceType, url: Url) -> Result<Self, Error> {
         Ok(Self {
             kind,
             url,
@@ -82,7 +124,7 @@ impl SourceId {
         match kind {
             "git" => {
                 let mut url = url.into_url()?;
-                let mut reference = GitReference::Branch("master".to_string());
+                let mut reference = GitReference::DefaultBranch;
                 for (k, v) in url.query_pairs() {
                     match &k[..] {
                         // Map older 'ref' to branch.
@@ -100,10 +142,15 @@ impl SourceId {
             }
             "registry" => {
                 let url = url.into_url()?;
-                Ok(SourceId::new(SourceKind::Registry, url)?
+                Ok(SourceId::new(SourceType::Registry, url)?
                     .with_precise(Some("locked".to_string())))
             }
-            "path" => Self::new(SourceKind::Path, url.into_url()?),
+            "sparse" => {
+                let url = url.into_url()?;
+                Ok(SourceId::new(SourceType::SparseRegistry, url)?
+                    .with_precise(Some("locked".to_string())))
+            }
+            "path" => Self::new(SourceType::Path, url.into_url()?),
             kind => fail!(ErrorKind::Parse, "unsupported source protocol: {}", kind),
         }
     }
@@ -112,27 +159,33 @@ impl SourceId {
     ///
     /// `path`: an absolute path.
     pub fn for_path(path: &Path) -> Result<Self, Error> {
-        Self::new(SourceKind::Path, path.into_url()?)
+        Self::new(SourceType::Path, path.into_url()?)
     }
 
     /// Creates a `SourceId` from a Git reference.
     pub fn for_git(url: &Url, reference: GitReference) -> Result<Self, Error> {
-        Self::new(SourceKind::Git(reference), url.clone())
+        Self::new(SourceType::Git(reference), url.clone())
     }
 
     /// Creates a SourceId from a registry URL.
     pub fn for_registry(url: &Url) -> Result<Self, Error> {
-        Self::new(SourceKind::Registry, url.clone())
+        Self::new(SourceType::Registry, url.clone())
+    }
+
+    /// Creates a SourceId from a sparse registry URL (without the `sparse+`
+    /// prefix).
+    pub fn for_sparse_registry(url: &Url) -> Result<Self, Error> {
+        Self::new(SourceType::SparseRegistry, url.clone())
     }
 
     /// Creates a SourceId from a local registry path.
     pub fn for_local_registry(path: &Path) -> Result<Self, Error> {
-        Self::new(SourceKind::LocalRegistry, path.into_url()?)
+        Self::new(SourceType::LocalRegistry, path.into_url()?)
     }
 
     /// Creates a `SourceId` from a directory path.
     pub fn for_directory(path: &Path) -> Result<Self, Error> {
-        Self::new(SourceKind::Directory, path.into_url()?)
+        Self::new(SourceType::Directory, path.into_url()?)
     }
 
     /// Gets this source URL.
@@ -162,15 +215,30 @@ impl SourceId {
 
     /// Returns `true` if this source is from a filesystem path.
     pub fn is_path(&self) -> bool {
-        self.kind == SourceKind::Path
+        self.kind == SourceType::Path
+    }
+
+    /// Get the filesystem path of a path source (with percent-encoded
+    /// characters decoded), or `None` for all other sources.
+    ///
+    /// Paths are converted for the current platform: on Windows, drive
+    /// letters are recognized (e.g. `file:///C:/src` is `C:\src`), while on
+    /// other platforms every path is below the root directory (e.g.
+    /// `/C:/src`). Paths needn't be valid UTF-8.
+    pub fn as_path(&self) -> Option<PathBuf> {
+        if !self.is_path() {
+            return None;
+        }
+
+        self.url.to_file_path().ok()
     }
 
     /// Returns `true` if this source is from a registry (either local or not).
     pub fn is_registry(&self) -> bool {
-        match self.kind {
-            SourceKind::Registry | SourceKind::LocalRegistry => true,
-            _ => false,
-        }
+        matches!(
+            self.kind,
+            SourceType::Registry | SourceType::SparseRegistry | SourceType::LocalRegistry
+        )
     }
 
     /// Returns `true` if this source is a "remote" registry.
@@ -178,14 +246,31 @@ impl SourceId {
     /// "remote" may also mean a file URL to a git index, so it is not
     /// necessarily "remote". This just means it is not `local-registry`.
     pub fn is_remote_registry(&self) -> bool {
-        self.kind == SourceKind::Registry
+        matches!(self.kind, SourceType::Registry | SourceType::SparseRegistry)
+    }
+
+    /// Returns `true` if this source is a registry accessed via the sparse
+    /// protocol.
+    pub fn is_sparse(&self) -> bool {
+        self.kind == SourceType::SparseRegistry
     }
 
     /// Returns `true` if this source from a Git repository.
     pub fn is_git(&self) -> bool {
+        matches!(self.kind, SourceType::Git(_))
+    }
+
+    /// Classify this source by where its code comes from, i.e. whether it's
+    /// crates.io or another registry, a git repository, or local.
+    pub fn kind(&self) -> SourceKind {
         match self.kind {
-            SourceKind::Git(_) => true,
-            _ => false,
+            SourceType::Registry | SourceType::SparseRegistry if self.is_default_registry() => {
+                SourceKind::CratesIo
+            }
+            SourceType::Registry | SourceType::SparseRegistry => SourceKind::AlternateRegistry,
+            SourceType::Git(_) => SourceKind::Git,
+            SourceType::Path => SourceKind::Path,
+            SourceType::LocalRegistry | SourceType::Directory => SourceKind::Local,
         }
     }
 
@@ -196,13 +281,77 @@ impl SourceId {
 
     /// Gets the Git reference if this is a git source, otherwise `None`.
     pub fn git_reference(&self) -> Option<&GitReference> {
-        if let SourceKind::Git(ref s) = self.kind {
+        if let SourceType::Git(ref s) = self.kind {
             Some(s)
         } else {
             None
         }
     }
 
+    /// Gets the commit a git source is locked to (i.e. the part of the
+    /// source after the `#`), or `None` if this isn't a git source or it
+    /// isn't locked to a commit.
+    pub fn git_revision(&self) -> Option<&str> {
+        if self.is_git() {
+            self.precise()
+        } else {
+            None
+        }
+    }
+
+    /// Is this a git source explicitly referring to the `master` branch?
+    ///
+    /// Lockfiles prior to `ResolveVersion::V3` can't distinguish this from
+    /// the repository's default branch.
+    pub(crate) fn is_explicit_master_branch(&self) -> bool {
+        match &self.kind {
+            SourceType::Git(GitReference::Branch(branch)) => branch == "master",
+            _ => false,
+        }
+    }
+
+    /// Encode an explicit `master` branch reference the way lockfiles prior
+    /// to `ResolveVersion::V3` do, i.e. as the default branch.
+    pub(crate) fn with_master_as_default_branch(&self) -> Self {
+        if self.is_explicit_master_branch() {
+            Self {
+                kind: SourceType::Git(GitReference::DefaultBranch),
+                ..self.clone()
+            }
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Does this git source refer to a branch, tag, or revision which is only
+    /// represented faithfully when percent-encoded?
+    ///
+    /// Lockfiles prior to `ResolveVersion::V4` store such references as-is.
+    pub(crate) fn needs_url_encoded_git_reference(&self) -> bool {
+        match &self.kind {
+            SourceType::Git(GitReference::Branch(s))
+            | SourceType::Git(GitReference::Tag(s))
+            | SourceType::Git(GitReference::Rev(s)) => url_encode(s) != *s,
+            _ => false,
+        }
+    }
+
+    /// Percent-encode the branch, tag, or revision of a git source the way
+    /// lockfiles as of `ResolveVersion::V4` do.
+    pub(crate) fn with_url_encoded_git_reference(&self) -> Self {
+        let reference = match &self.kind {
+            SourceType::Git(GitReference::Branch(b)) => GitReference::Branch(url_encode(b)),
+            SourceType::Git(GitReference::Tag(s)) => GitReference::Tag(url_encode(s)),
+            SourceType::Git(GitReference::Rev(s)) => GitReference::Rev(url_encode(s)),
+            _ => return self.clone(),
+        };
+
+        Self {
+            kind: SourceType::Git(reference),
+            ..self.clone()
+        }
+    }
+
     /// Creates a new `SourceId` from this source with the given `precise`.
     pub fn with_precise(&self, v: Option<String>) -> Self {
         Self {
@@ -211,9 +360,79 @@ impl SourceId {
         }
     }
 
-    /// Returns `true` if the remote registry is the standard <https://crates.io>.
+    /// Returns `true` if the remote registry is the standard <https://crates.io>,
+    /// accessed via either the git or the sparse protocol.
     pub fn is_default_registry(&self) -> bool {
-        self.kind == SourceKind::Registry && self.url.as_str() == CRATES_IO_INDEX
+        match self.kind {
+            SourceType::Registry => self.url.as_str() == CRATES_IO_INDEX,
+            SourceType::SparseRegistry => self.url.as_str() == CRATES_IO_SPARSE_INDEX,
+            _ => false,
+        }
+    }
+
+    /// Get the protocol used to access the crates.io index, or `None` if
+    /// this source isn't crates.io.
+    pub fn crates_io_protocol(&self) -> Option<CratesIoProtocol> {
+        match self.kind {
+            _ if !self.is_default_registry() => None,
+            SourceType::SparseRegistry => Some(CratesIoProtocol::Sparse),
+            _ => Some(CratesIoProtocol::Git),
+        }
+    }
+
+    /// Rewrite this source to access the crates.io index via the given
+    /// protocol if it refers to crates.io, leaving all other sources
+    /// unchanged.
+    pub fn with_crates_io_protocol(&self, protocol: CratesIoProtocol) -> Self {
+        match (self.crates_io_protocol(), protocol) {
+            (Some(CratesIoProtocol::Sparse), CratesIoProtocol::Git) => Self {
+                kind: SourceType::Registry,
+                url: CRATES_IO_INDEX.into_url().unwrap(),
+                ..self.clone()
+            },
+            (Some(CratesIoProtocol::Git), CratesIoProtocol::Sparse) => Self {
+                kind: SourceType::SparseRegistry,
+                url: CRATES_IO_SPARSE_INDEX.into_url().unwrap(),
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Normalize sources referring to the crates.io index via the sparse
+    /// protocol into the canonical git form (i.e. [`CRATES_IO_INDEX`]),
+    /// leaving all other sources unchanged.
+    ///
+    /// Useful for comparing lockfiles generated before and after the switch
+    /// to the sparse protocol, which otherwise differ in every crates.io
+    /// package's source.
+    pub fn normalize_crates_io(&self) -> Self {
+        self.with_crates_io_protocol(CratesIoProtocol::Git)
+    }
+
+    /// Get a canonical form of this source for comparing sources, e.g. when
+    /// deduplicating packages across lockfiles, so that sources which refer
+    /// to the same location compare equal.
+    ///
+    /// Trailing slashes are removed from the URL (which always has a
+    /// lowercase host, as URLs are normalized when parsed), and crates.io is
+    /// always referred to using [`CRATES_IO_INDEX`] (i.e. the same as
+    /// [`SourceId::normalize_crates_io`]), regardless of whether it's
+    /// accessed via the git or the sparse protocol.
+    ///
+    /// The original source is left unchanged, so use it (rather than the
+    /// normalized one) when serializing lockfiles, as Cargo would otherwise
+    /// consider the sources changed.
+    pub fn normalized(&self) -> Self {
+        let mut url = self.url.clone();
+        let path = url.path().trim_end_matches('/').to_owned();
+        url.set_path(&path);
+
+        Self {
+            url,
+            ..self.clone()
+        }
+        .normalize_crates_io()
     }
 }
 
@@ -229,12 +448,12 @@ impl fmt::Display for SourceId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SourceId {
-                kind: SourceKind::Path,
+                kind: SourceType::Path,
                 ref url,
                 ..
             } => write!(f, "path+{}", url),
             SourceId {
-                kind: SourceKind::Git(ref reference),
+                kind: SourceType::Git(ref reference),
                 ref url,
                 ref precise,
                 ..
@@ -249,17 +468,22 @@ impl fmt::Display for SourceId {
                 Ok(())
             }
             SourceId {
-                kind: SourceKind::Registry,
+                kind: SourceType::Registry,
                 ref url,
                 ..
             } => write!(f, "registry+{}", url),
             SourceId {
-                kind: SourceKind::LocalRegistry,
+                kind: SourceType::SparseRegistry,
+                ref url,
+                ..
+            } => write!(f, "sparse+{}", url),
+            SourceId {
+                kind: SourceType::LocalRegistry,
                 ref url,
                 ..
             } => write!(f, "local-registry+{}", url),
             SourceId {
-                kind: SourceKind::Directory,
+                kind: SourceType::Directory,
                 ref url,
                 ..
             } => write!(f, "directory+{}", url),
@@ -295,14 +519,17 @@ pub enum GitReference {
 
     /// From a specific revision.
     Rev(String),
+
+    /// From the HEAD of the repository's default branch.
+    DefaultBranch,
 }
 
 impl GitReference {
     /// Returns a `Display`able view of this git reference, or None if using
-    /// the head of the "master" branch
+    /// the head of the default branch
     pub fn pretty_ref(&self) -> Option<PrettyRef<'_>> {
         match *self {
-            GitReference::Branch(ref s) if *s == "master" => None,
+            GitReference::DefaultBranch => None,
             _ => Some(PrettyRef { inner: self }),
         }
     }
@@ -319,6 +546,7 @@ impl<'a> fmt::Display for PrettyRef<'a> {
             GitReference::Branch(ref b) => write!(f, "branch={}", b),
             GitReference::Tag(ref s) => write!(f, "tag={}", s),
             GitReference::Rev(ref s) => write!(f, "rev={}", s),
+            GitReference::DefaultBranch => Ok(()),
         }
     }
 }
@@ -329,15 +557,246 @@ trait IntoUrl {
     fn into_url(self) -> Result<Url, Error>;
 }
 
-impl<'a> IntoUrl for &'a str {
+impl IntoUrl for &str {
     fn into_url(self) -> Result<Url, Error> {
         Url::parse(self).map_err(|s| format_err!(ErrorKind::Parse, "invalid url `{}`: {}", self, s))
     }
 }
 
-impl<'a> IntoUrl for &'a Path {
+impl IntoUrl for &Path {
     fn into_url(self) -> Result<Url, Error> {
         Url::from_file_path(self)
             .map_err(|()| format_err!(ErrorKind::Parse, "invalid path url `{}`", self.display()))
     }
 }
+
+/// Percent-encode a git reference the way lockfiles as of
+/// `ResolveVersion::V4` do
+fn url_encode(s: &str) -> String {
+    form_urlencoded::byte_serialize(s.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CratesIoProtocol, GitReference, SourceId, SourceKind, CRATES_IO_INDEX};
+    use std::path::{Path, PathBuf};
+
+    const SPARSE_CRATES_IO: &str = "sparse+https://index.crates.io/";
+
+    #[test]
+    fn sparse_round_trip() {
+        let source = SPARSE_CRATES_IO.parse::<SourceId>().unwrap();
+        assert!(source.is_sparse());
+        assert!(source.is_remote_registry());
+        assert!(source.is_default_registry());
+        assert_eq!(source.to_string(), SPARSE_CRATES_IO);
+    }
+
+    #[test]
+    fn normalize_crates_io() {
+        let git = format!("registry+{}", CRATES_IO_INDEX)
+            .parse::<SourceId>()
+            .unwrap();
+
+        let sparse = SPARSE_CRATES_IO.parse::<SourceId>().unwrap();
+
+        assert_ne!(sparse, git);
+        assert_eq!(sparse.normalize_crates_io(), git);
+        assert_eq!(git.normalize_crates_io(), git);
+    }
+
+    #[test]
+    fn normalize_other_sparse_registry() {
+        let source = "sparse+https://example.com/index/"
+            .parse::<SourceId>()
+            .unwrap();
+
+        assert!(!source.is_default_registry());
+        assert_eq!(source.normalize_crates_io(), source);
+    }
+
+    #[test]
+    fn crates_io_protocol() {
+        let git = format!("registry+{}", CRATES_IO_INDEX)
+            .parse::<SourceId>()
+            .unwrap();
+        let sparse = SPARSE_CRATES_IO.parse::<SourceId>().unwrap();
+        let other = "sparse+https://example.com/index/"
+            .parse::<SourceId>()
+            .unwrap();
+
+        assert_eq!(git.crates_io_protocol(), Some(CratesIoProtocol::Git));
+        assert_eq!(sparse.crates_io_protocol(), Some(CratesIoProtocol::Sparse));
+        assert_eq!(other.crates_io_protocol(), None);
+
+        assert_eq!(
+            git.with_crates_io_protocol(CratesIoProtocol::Sparse),
+            sparse
+        );
+        assert_eq!(sparse.with_crates_io_protocol(CratesIoProtocol::Git), git);
+        assert_eq!(
+            sparse.with_crates_io_protocol(CratesIoProtocol::Sparse),
+            sparse
+        );
+        assert_eq!(other.with_crates_io_protocol(CratesIoProtocol::Git), other);
+    }
+
+    #[test]
+    fn normalized() {
+        let git = format!("registry+{}", CRATES_IO_INDEX)
+            .parse::<SourceId>()
+            .unwrap();
+
+        let sources = [
+            SPARSE_CRATES_IO,
+            "sparse+https://INDEX.crates.io",
+            "registry+https://github.com/rust-lang/crates.io-index/",
+            "registry+https://GitHub.com/rust-lang/crates.io-index",
+        ];
+
+        for source in &sources {
+            let source = source.parse::<SourceId>().unwrap();
+            assert_eq!(source.normalized(), git, "{}", source);
+        }
+
+        let sparse = "sparse+https://Registry.example.com/index/"
+            .parse::<SourceId>()
+            .unwrap();
+        assert_eq!(
+            sparse.normalized().to_string(),
+            "sparse+https://registry.example.com/index"
+        );
+        assert_ne!(sparse.normalized(), git);
+        assert_eq!(
+            sparse.to_string(),
+            "sparse+https://registry.example.com/index/"
+        );
+    }
+
+    #[test]
+    fn kinds() {
+        let kinds = [
+            (
+                format!("registry+{}", CRATES_IO_INDEX),
+                SourceKind::CratesIo,
+            ),
+            (SPARSE_CRATES_IO.to_owned(), SourceKind::CratesIo),
+            (
+                "registry+https://github.com/example/index".to_owned(),
+                SourceKind::AlternateRegistry,
+            ),
+            (
+                "sparse+https://registry.example.com/index/".to_owned(),
+                SourceKind::AlternateRegistry,
+            ),
+            (
+                "git+https://github.com/rust-lang/crates.io-index#0123456789abcdef".to_owned(),
+                SourceKind::Git,
+            ),
+            ("path+file:///src/example".to_owned(), SourceKind::Path),
+        ];
+
+        for (url, kind) in &kinds {
+            assert_eq!(url.parse::<SourceId>().unwrap().kind(), *kind, "{}", url);
+        }
+
+        let vendor = Path::new("/vendor");
+        let local = SourceId::for_local_registry(vendor).unwrap();
+        assert_eq!(local.kind(), SourceKind::Local);
+        let directory = SourceId::for_directory(vendor).unwrap();
+        assert_eq!(directory.kind(), SourceKind::Local);
+    }
+
+    #[test]
+    fn as_path() {
+        let source = "path+file:///home/user/my%20crate"
+            .parse::<SourceId>()
+            .unwrap();
+        assert_eq!(source.as_path(), Some(PathBuf::from("/home/user/my crate")));
+
+        let source = SPARSE_CRATES_IO.parse::<SourceId>().unwrap();
+        assert_eq!(source.as_path(), None);
+    }
+
+    #[test]
+    fn as_path_with_drive_letter() {
+        let source = "path+file:///C:/Users/me/crate"
+            .parse::<SourceId>()
+            .unwrap();
+        let path = source.as_path().unwrap();
+
+        if cfg!(windows) {
+            assert_eq!(path, PathBuf::from(r"C:\Users\me\crate"));
+        } else {
+            assert_eq!(path, PathBuf::from("/C:/Users/me/crate"));
+            assert!(path.is_absolute());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn as_path_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let source = "path+file:///home/user/%FFcrate"
+            .parse::<SourceId>()
+            .unwrap();
+        let expected = Path::new(OsStr::from_bytes(b"/home/user/\xFFcrate"));
+        assert_eq!(source.as_path().as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn git_default_branch() {
+        let url = "git+https://github.com/example/repo#0123456789abcdef";
+        let source = url.parse::<SourceId>().unwrap();
+        assert_eq!(source.git_reference(), Some(&GitReference::DefaultBranch));
+        assert_eq!(source.to_string(), url);
+    }
+
+    #[test]
+    fn git_explicit_master_branch() {
+        let url = "git+https://github.com/example/repo?branch=master#0123456789abcdef";
+        let source = url.parse::<SourceId>().unwrap();
+        assert!(source.is_explicit_master_branch());
+        assert_eq!(source.to_string(), url);
+        assert_eq!(
+            source.with_master_as_default_branch().to_string(),
+            "git+https://github.com/example/repo#0123456789abcdef"
+        );
+    }
+
+    #[test]
+    fn git_references() {
+        let sources = [
+            (
+                "git+https://github.com/example/repo?rev=0123abc#0123456789abcdef",
+                GitReference::Rev("0123abc".into()),
+            ),
+            (
+                "git+https://github.com/example/repo?branch=next#0123456789abcdef",
+                GitReference::Branch("next".into()),
+            ),
+            (
+                "git+https://github.com/example/repo?tag=v1.0.0#0123456789abcdef",
+                GitReference::Tag("v1.0.0".into()),
+            ),
+        ];
+
+        for (url, reference) in &sources {
+            let source = url.parse::<SourceId>().unwrap();
+            assert_eq!(source.git_reference(), Some(reference));
+            assert_eq!(source.git_revision(), Some("0123456789abcdef"));
+            assert_eq!(source.url().as_str(), "https://github.com/example/repo");
+            assert_eq!(source.to_string(), *url);
+        }
+
+        let unlocked = "git+https://github.com/example/repo?branch=next"
+            .parse::<SourceId>()
+            .unwrap();
+        assert_eq!(unlocked.git_revision(), None);
+
+        let registry = SPARSE_CRATES_IO.parse::<SourceId>().unwrap();
+        assert_eq!(registry.git_reference(), None);
+        assert_eq!(registry.git_revision(), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Verifying package checksums against `.crate` files
+
+use super::{Checksum, Package};
+use crate::{
+    error::{Error, ErrorKind},
+    hash::{ContentHasher, Sha256},
+};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+impl Package {
+    /// Compute the SHA-256 digest of the given `.crate` file (e.g. from a
+    /// vendor directory) and check whether it matches this package's
+    /// checksum.
+    ///
+    /// It's an [`ErrorKind::Parse`] error if this package has no checksum
+    /// (e.g. because it's a path or git dependency, or the checksum is kept
+    /// in the `[metadata]` table of a V1 lockfile), and an [`ErrorKind::Io`]
+    /// error if the file can't be read.
+    ///
+    /// The `verify` Cargo feature must be enabled to use this.
+    pub fn verify_checksum(&self, crate_file: &Path) -> Result<bool, Error> {
+        let expected = match &self.checksum {
+            Some(checksum) => checksum,
+            None => fail!(
+                ErrorKind::Parse,
+                "no checksum recorded for {} {}",
+                self.name,
+                self.version
+            ),
+        };
+
+        let digest = sha256_file(crate_file).map_err(|e| {
+            format_err!(
+                ErrorKind::Io,
+                "couldn't read {}: {}",
+                crate_file.display(),
+                e
+            )
+        })?;
+
+        Ok(*expected == Checksum::from(digest))
+    }
+}
+
+/// Compute the SHA-256 digest of a file without reading it into memory all
+/// at once
+fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::default();
+    let mut buffer = [0; 8192];
+
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(hasher.finalize_bytes()),
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ErrorKind, package::Package};
+    use std::path::Path;
+
+    /// Example `.crate` file with a known SHA-256 digest
+    const CRATE_FILE: &str = "tests/support/example-0.1.0.crate";
+
+    /// Build a package with the given checksum
+    fn package(checksum: Option<&str>) -> Package {
+        Package {
+            name: "example".parse().unwrap(),
+            version: "0.1.0".parse().unwrap(),
+            source: None,
+            checksum: checksum.map(|c| c.parse().unwrap()),
+            dependencies: vec![],
+            replace: None,
+        }
+    }
+
+    #[test]
+    fn matching_checksum() {
+        let package = package(Some(
+            "73311eca750cfe497d2f5766044b2c6a2f19e62027ebd1a06a02903d3d624d1f",
+        ));
+        assert!(package.verify_checksum(Path::new(CRATE_FILE)).unwrap());
+    }
+
+    #[test]
+    fn mismatched_checksum() {
+        let package = package(Some(
+            "414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449",
+        ));
+        assert!(!package.verify_checksum(Path::new(CRATE_FILE)).unwrap());
+    }
+
+    #[test]
+    fn missing_checksum() {
+        let err = package(None)
+            .verify_checksum(Path::new(CRATE_FILE))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn missing_file() {
+        let package = package(Some(
+            "73311eca750cfe497d2f5766044b2c6a2f19e62027ebd1a06a02903d3d624d1f",
+        ));
+        let err = package
+            .verify_checksum(Path::new("tests/support/nonexistent.crate"))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+    }
+}
@@ -0,0 +1,123 @@
+//! Version requirement helpers (i.e. semver math) for policy checks
+
+use semver::{Version, VersionReq};
+
+/// Find the lowest (non-prerelease) version satisfying all of the given
+/// requirements, if any, e.g. to determine whether duplicate versions of a
+/// crate could be unified into a single one.
+///
+/// Returns `Some(Version::new(0, 0, 0))` if no requirements are given.
+pub fn common_version(reqs: &[VersionReq]) -> Option<Version> {
+    candidates(reqs)
+        .into_iter()
+        .find(|version| reqs.iter().all(|req| req.matches(version)))
+}
+
+/// Do the given requirements have a common satisfying version?
+pub fn is_satisfiable(reqs: &[VersionReq]) -> bool {
+    common_version(reqs).is_some()
+}
+
+/// Are the given versions semver-compatible, i.e. could one be used in
+/// place of the other under Cargo's rules (the same major version, or the
+/// same leftmost non-zero component for `0.x` versions)?
+pub fn is_compatible(a: &Version, b: &Version) -> bool {
+    if a.major != b.major {
+        false
+    } else if a.major > 0 {
+        true
+    } else if a.minor != b.minor {
+        false
+    } else {
+        a.minor > 0 || a.patch == b.patch
+    }
+}
+
+/// Compute the (sorted) versions which could possibly be the lowest version
+/// satisfying all of the given requirements.
+///
+/// Requirements are intersections of comparators, so a version satisfying
+/// all of them exists iff the lowest version in the intersection of their
+/// ranges exists. That version is always either `0.0.0`, one of the versions
+/// mentioned by a comparator, or the version immediately after one of them.
+fn candidates(reqs: &[VersionReq]) -> Vec<Version> {
+    let mut candidates = vec![Version::new(0, 0, 0)];
+
+    for req in reqs {
+        // `semver` doesn't expose the comparators of a `VersionReq`, but its
+        // string form contains all of the versions they mention
+        let req = req.to_string();
+
+        for part in req
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|part| !part.is_empty())
+        {
+            let numbers = part
+                .split('.')
+                .map(|n| n.parse::<u64>().unwrap_or(0))
+                .collect::<Vec<_>>();
+
+            let number = |i: usize| numbers.get(i).copied().unwrap_or(0);
+            let (major, minor, patch) = (number(0), number(1), number(2));
+
+            candidates.push(Version::new(major, minor, patch));
+            candidates.push(Version::new(major, minor, patch + 1));
+            candidates.push(Version::new(major, minor + 1, 0));
+            candidates.push(Version::new(major + 1, 0, 0));
+        }
+    }
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{common_version, is_compatible, is_satisfiable};
+    use semver::{Version, VersionReq};
+
+    /// Parse the given version requirements
+    fn reqs(reqs: &[&str]) -> Vec<VersionReq> {
+        reqs.iter()
+            .map(|req| VersionReq::parse(req).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn compatible_requirements() {
+        let expected = Some(Version::parse("1.4.0").unwrap());
+        assert_eq!(common_version(&reqs(&["^1.2", "^1.4", "< 1.9"])), expected);
+
+        let expected = Some(Version::parse("0.3.2").unwrap());
+        assert_eq!(common_version(&reqs(&["~0.3.1", "> 0.3.1"])), expected);
+
+        let expected = Some(Version::parse("2.0.0").unwrap());
+        assert_eq!(common_version(&reqs(&["> 1", "*"])), expected);
+
+        assert!(is_satisfiable(&reqs(&[">= 1.0, < 2.0", "1.*", "=1.5.2"])));
+        assert!(is_satisfiable(&[]));
+    }
+
+    #[test]
+    fn conflicting_requirements() {
+        assert!(!is_satisfiable(&reqs(&["^1.2", "^2.0"])));
+        assert!(!is_satisfiable(&reqs(&["^0.2", "^0.3"])));
+        assert!(!is_satisfiable(&reqs(&["~1.2.3", ">= 1.3"])));
+        assert!(!is_satisfiable(&reqs(&["= 1.2.3", "= 1.2.4"])));
+        assert!(!is_satisfiable(&reqs(&["> 1.2.3, < 1.2.4"])));
+    }
+
+    #[test]
+    fn compatible_versions() {
+        let compatible = |a: &str, b: &str| {
+            is_compatible(&Version::parse(a).unwrap(), &Version::parse(b).unwrap())
+        };
+
+        assert!(compatible("1.2.3", "1.9.0"));
+        assert!(compatible("0.3.1", "0.3.7"));
+        assert!(!compatible("1.2.3", "2.0.0"));
+        assert!(!compatible("0.3.1", "0.4.0"));
+        assert!(!compatible("0.0.1", "0.0.2"));
+    }
+}
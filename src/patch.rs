@@ -1,13 +1,14 @@
 //! The `[[patch]]` section
 
-use crate::dependency::Dependency;
+use crate::package::Package;
 use serde::{Deserialize, Serialize};
 
 /// The `[[patch]]` section of `Cargo.lock`
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Patch {
-    /// Unused patches
-    pub unused: Vec<Dependency>,
+    /// Unused patches, which are recorded the same way as packages (i.e.
+    /// possibly including a checksum), in the order Cargo wrote them
+    pub unused: Vec<Package>,
 }
 
 impl Patch {
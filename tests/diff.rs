@@ -0,0 +1,96 @@
+//! Lockfile diff integration test
+
+use cargo_lock::{
+    lockfile::diff::{Change, SourceChange},
+    Lockfile,
+};
+
+/// Load the old and new lockfiles used by these tests
+fn load_example_lockfiles() -> (Lockfile, Lockfile) {
+    (
+        Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap(),
+        Lockfile::load("tests/support/Cargo.lock.source-swap-example").unwrap(),
+    )
+}
+
+/// Identical lockfiles have no differences
+#[test]
+fn diff_identical_lockfiles() {
+    let (old, _) = load_example_lockfiles();
+    assert!(old.diff(&old).is_empty());
+}
+
+/// Find added and removed packages, and ones whose version changed
+#[test]
+fn diff_added_removed_and_version_changed() {
+    let (old, new) = load_example_lockfiles();
+    let diff = old.diff(&new);
+
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].name.as_str(), "extra");
+
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].name.as_str(), "master-branch");
+
+    assert_eq!(diff.version_changed.len(), 1);
+    let change = &diff.version_changed[0];
+    assert_eq!(change.name.as_str(), "default-branch");
+    assert_eq!(change.old.to_string(), "0.2.0");
+    assert_eq!(change.new.to_string(), "0.2.1");
+}
+
+/// Ensure a crate redirected to a git fork without a version change is flagged
+#[test]
+fn diff_same_version_source_swap() {
+    let (old, new) = load_example_lockfiles();
+    let diff = old.diff(&new);
+
+    assert_eq!(
+        diff.source_changed,
+        [SourceChange {
+            name: "serde".parse().unwrap(),
+            version: "1.0.104".parse().unwrap(),
+            old: Some(
+                "registry+https://github.com/rust-lang/crates.io-index"
+                    .parse()
+                    .unwrap()
+            ),
+            new: Some(
+                "git+https://github.com/example-fork/serde?branch=patched\
+                 #0123456789abcdef0123456789abcdef01234567"
+                    .parse()
+                    .unwrap()
+            ),
+        }]
+    );
+
+    // The reverse diff reports the same swap the other way around
+    let reverse = new.diff(&old);
+    assert_eq!(reverse.source_changed.len(), 1);
+    assert_eq!(reverse.source_changed[0].old, diff.source_changed[0].new);
+}
+
+/// Classify the changes between lockfiles
+#[test]
+fn changeset() {
+    let (old, new) = load_example_lockfiles();
+    assert!(old.changeset(&old).is_empty());
+
+    let changes = new.changeset(&old).changes;
+    let names = changes
+        .iter()
+        .map(|change| change.name().as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["default-branch", "extra", "master-branch", "serde"]);
+    assert!(matches!(&changes[0], Change::Upgraded(change) if change.new.to_string() == "0.2.1"));
+    assert!(matches!(&changes[1], Change::Added(_)));
+    assert!(matches!(&changes[2], Change::Removed(_)));
+    assert!(matches!(&changes[3], Change::SourceChanged(_)));
+
+    // Going back the other way, the upgrade becomes a downgrade
+    let changes = old.changeset(&new).changes;
+    assert!(matches!(&changes[0], Change::Downgraded(change) if change.new.to_string() == "0.2.0"));
+    assert!(matches!(&changes[1], Change::Removed(_)));
+    assert!(matches!(&changes[2], Change::Added(_)));
+}
@@ -2,13 +2,32 @@
 
 // TODO(tarcieri): add more example `Cargo.lock` files which cover more scenarios
 
-use cargo_lock::{metadata, Lockfile, ResolveVersion, Version};
+use cargo_lock::{
+    hash::ContentHasher,
+    lockfile::{version::DowngradeLoss, Newline},
+    metadata,
+    package::source::GitReference,
+    CratesIoProtocol, Dependency, ErrorKind, Lockfile, LockfileBuilder, Metadata, MetadataExt,
+    Name, Package, PackageId, ResolveVersion, SerializeOptions, SourceId, SourceKind,
+    ValidationError, Version,
+};
+use std::{collections::BTreeSet, fs};
 
 /// Load our own `Cargo.lock` file for use in tests
 fn load_our_lockfile() -> Lockfile {
     Lockfile::load("Cargo.lock").unwrap()
 }
 
+/// Load a lockfile from its directory, and fail to load a manifest instead
+#[test]
+fn load_from_directory() {
+    assert_eq!(Lockfile::load(".").unwrap(), load_our_lockfile());
+
+    let err = Lockfile::load("Cargo.toml").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotALockfile);
+    assert!(err.msg().starts_with("Cargo.toml is a Cargo.toml manifest"));
+}
+
 /// Load this crate's own V2 `Cargo.lock` file
 #[test]
 fn load_our_own_v2_lockfile() {
@@ -42,6 +61,34 @@ fn load_example_v1_lockfile() {
     );
 }
 
+/// Typed access to V1 `[metadata]` checksums
+#[test]
+fn metadata_checksums() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v1-example").unwrap();
+    let checksums = lockfile.metadata.checksums().collect::<Vec<_>>();
+    assert_eq!(checksums.len(), 136);
+
+    let (dependency, checksum) = &checksums[0];
+    assert_eq!(dependency.name.as_str(), "adler32");
+    assert_eq!(
+        checksum.to_string(),
+        "5d2e7343e7fc9de883d1b0341e0b13970f764c14101234857d2ddafa1cb1cac2"
+    );
+
+    // Writing the checksums back reproduces the original entries exactly
+    let mut metadata = Metadata::new();
+
+    for (dependency, checksum) in &checksums {
+        assert_eq!(metadata.set_checksum(dependency, checksum), None);
+    }
+
+    assert_eq!(metadata, lockfile.metadata);
+
+    // Other entries are skipped
+    metadata.insert("foo".parse().unwrap(), "bar".parse().unwrap());
+    assert_eq!(metadata.checksums().count(), 136);
+}
+
 /// Load example V2 `Cargo.lock` file (from rustc)
 #[test]
 fn load_example_v2_lockfile() {
@@ -82,10 +129,1363 @@ fn serialize_v1_to_v2() {
     assert_eq!(lockfile.packages, lockfile2.packages);
 }
 
+/// Serialize the given lockfile with `Lockfile::to_writer_pretty`
+fn to_string_pretty(lockfile: &Lockfile) -> String {
+    let mut output = Vec::new();
+    lockfile.to_writer_pretty(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Strip the leading comment header (and subsequent blank lines) of a lockfile
+fn strip_header(lockfile: &str) -> &str {
+    let body_start = lockfile
+        .find("\n[")
+        .map(|i| i + 1)
+        .unwrap_or_else(|| lockfile.len());
+
+    &lockfile[body_start..]
+}
+
+/// Ensure invalid package versions are rejected with an error naming the
+/// offending package
+#[test]
+fn load_invalid_version() {
+    let err = Lockfile::load("tests/support/Cargo.lock.invalid-version-example").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    assert!(err
+        .msg()
+        .starts_with("invalid version `1.0.x` for package `broken`: "));
+}
+
+/// Peeking at the resolve version agrees with loading the whole lockfile
+#[test]
+fn peek_resolve_version() {
+    for entry in fs::read_dir("tests/support").unwrap() {
+        let path = entry.unwrap().path();
+
+        if !path.to_str().unwrap().contains("Cargo.lock") {
+            continue;
+        }
+
+        if let Ok(lockfile) = Lockfile::load(&path) {
+            let file = fs::File::open(&path).unwrap();
+            assert_eq!(ResolveVersion::peek(file).unwrap(), lockfile.version);
+        }
+    }
+
+    let peek = |toml: &str| ResolveVersion::peek(toml.as_bytes());
+    assert_eq!(
+        peek("# comment\nversion = 4 # comment\n").unwrap(),
+        ResolveVersion::V4
+    );
+    assert_eq!(peek("").unwrap(), ResolveVersion::V2);
+    assert_eq!(
+        peek("version = 9\n").unwrap_err().kind(),
+        ErrorKind::UnsupportedVersion
+    );
+}
+
+/// Lockfiles without any `[[package]]` entries are valid
+#[test]
+fn load_empty_lockfile() {
+    let toml = "# This file is automatically @generated by Cargo.\n\
+                # It is not intended for manual editing.\n\
+                version = 3\n";
+
+    let lockfile = toml.parse::<Lockfile>().unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V3);
+    assert!(lockfile.packages.is_empty());
+    assert_eq!(lockfile.to_string(), toml);
+
+    // Without a `version` field, the resolve version is still detected
+    let lockfile = "[[patch.unused]]\nname = \"a\"\nversion = \"1.0.0\"\n"
+        .parse::<Lockfile>()
+        .unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V2);
+    assert!(lockfile.packages.is_empty());
+}
+
+/// Ensure failure modes can be distinguished by their `ErrorKind`
+#[test]
+fn error_kinds() {
+    let kind = |toml: &str| toml.parse::<Lockfile>().unwrap_err().kind();
+    let package = "[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n";
+
+    assert_eq!(kind("[[package]]"), ErrorKind::Parse);
+    assert_eq!(kind("version = 9"), ErrorKind::UnsupportedVersion);
+    assert_eq!(kind(""), ErrorKind::MissingPackages);
+    assert_eq!(kind("# comment\n"), ErrorKind::MissingPackages);
+    assert_eq!(kind("[workspace]\nmembers = []\n"), ErrorKind::NotALockfile);
+    assert_eq!(
+        kind(&format!("{}\n{}", package, package)),
+        ErrorKind::DuplicatePackage
+    );
+    assert_eq!(
+        kind(&format!("{}dependencies = [\"missing\"]\n", package)),
+        ErrorKind::DanglingDependency
+    );
+}
+
+/// Abbreviated dependencies matching several sourced packages are rejected
+#[test]
+fn ambiguous_dependency() {
+    let toml = r#"version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["foo 1.0.0"]
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "git+https://github.com/example/foo#0123456789abcdef0123456789abcdef01234567"
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    let err = toml.parse::<Lockfile>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    assert!(err.to_string().contains("ambiguous dependency"));
+}
+
+/// Ensure pretty serialization of this crate's own V2 `Cargo.lock` is
+/// byte-identical to Cargo's
+#[test]
+fn pretty_serialize_our_own_lockfile() {
+    let original = fs::read_to_string("Cargo.lock").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+    assert_eq!(to_string_pretty(&lockfile), original);
+}
+
+/// Ensure pretty serialization of the example V1 `Cargo.lock` (from the
+/// Cargo project itself) is byte-identical to Cargo's, aside from the header
+#[test]
+fn pretty_serialize_example_v1_lockfile() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v1-example").unwrap();
+    let mut lockfile = original.parse::<Lockfile>().unwrap();
+    let reserialized = to_string_pretty(&lockfile);
+    assert!(reserialized.starts_with("# Example non-trivial `Cargo.lock` file.\n"));
+    assert_eq!(strip_header(&reserialized), strip_header(&original));
+
+    lockfile.preamble = None;
+    let reserialized = to_string_pretty(&lockfile);
+
+    assert!(reserialized.starts_with("# This file is automatically @generated by Cargo.\n"));
+    assert_eq!(strip_header(&reserialized), strip_header(&original));
+}
+
+/// Ensure pretty serialization of the example V2 `Cargo.lock` (from rustc)
+/// is byte-identical to Cargo's
+#[test]
+fn pretty_serialize_example_v2_lockfile() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v2-example").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+    assert_eq!(to_string_pretty(&lockfile), original);
+}
+
+/// Ensure lockfiles reformatted with tabs, CRLF line endings, and extra blank
+/// lines (i.e. still valid TOML) parse and are normalized to Cargo's format
+#[test]
+fn pretty_serialize_unusual_whitespace() {
+    let original = fs::read_to_string("Cargo.lock").unwrap();
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.whitespace-example").unwrap();
+
+    assert_eq!(lockfile, original.parse::<Lockfile>().unwrap());
+    assert_eq!(to_string_pretty(&lockfile), original);
+}
+
+/// Stream a lockfile to a writer, which should match `to_string()`
+#[test]
+fn write_to() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v2-example").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+
+    let mut output = Vec::new();
+    lockfile.write_to(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), original);
+    assert_eq!(lockfile.to_string(), original);
+}
+
+/// Report the location of parse errors
+#[test]
+fn parse_error_locations() {
+    let err = "[[package]\nname = \"a\"\n"
+        .parse::<Lockfile>()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    assert_eq!(err.line_col(), Some((1, 11)));
+
+    let v3 = fs::read_to_string("tests/support/Cargo.lock.v3-example").unwrap();
+    let err = v3
+        .replace("name = \"master-branch\"\n", "")
+        .parse::<Lockfile>()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    assert_eq!(err.line_col(), Some((19, 1)));
+    assert!(err
+        .msg()
+        .contains("missing field `name` for [[package]] #3"));
+}
+
+/// Construct lockfiles in code
+#[test]
+fn builder() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v1-example").unwrap();
+    let mut builder = LockfileBuilder::new();
+
+    for package in original.packages.iter().rev() {
+        builder = builder.package(package.clone());
+    }
+
+    for (key, value) in &original.metadata {
+        builder = builder.metadata(key.clone(), value.clone());
+    }
+
+    let lockfile = builder.build().unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V1);
+    assert_eq!(lockfile.packages, original.packages);
+    assert_eq!(lockfile.metadata, original.metadata);
+
+    let package = original.packages[0].clone();
+    let lockfile = LockfileBuilder::new()
+        .package(package.clone())
+        .patch(package.clone())
+        .resolve_version(ResolveVersion::V3)
+        .build()
+        .unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V3);
+    assert_eq!(lockfile.patch.unused, vec![package.clone()]);
+
+    let errors = LockfileBuilder::new()
+        .package(package.clone())
+        .package(package.clone())
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        errors,
+        [ValidationError::DuplicatePackage(PackageId::from(&package))]
+    );
+}
+
+/// Lockfiles constructed in code survive a serialization round trip
+#[test]
+fn builder_round_trip() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let mut builder = LockfileBuilder::new().resolve_version(ResolveVersion::V3);
+
+    for package in &original.packages {
+        builder = builder.package(package.clone());
+    }
+
+    let lockfile = builder.build().unwrap();
+    assert_eq!(lockfile.preamble, None);
+
+    let reparsed = lockfile.to_string().parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed, lockfile);
+}
+
+/// Report all consistency problems at once
+#[test]
+fn validate() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    assert_eq!(lockfile.validate(), Ok(()));
+
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.version-mismatch-example").unwrap();
+    let serde = lockfile.packages[1].clone();
+    lockfile.packages.push(serde.clone());
+    lockfile.packages[0].dependencies.push(Dependency {
+        name: "missing".parse().unwrap(),
+        version: Version::new(1, 0, 0),
+        source: None,
+    });
+
+    let errors = lockfile.validate().unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            ValidationError::DuplicatePackage(PackageId::from(&serde)),
+            ValidationError::DanglingDependency {
+                package: PackageId::from(&lockfile.packages[0]),
+                dependency: lockfile.packages[0].dependencies[1].clone(),
+            },
+            ValidationError::MissingChecksum(PackageId::from(&serde)),
+            ValidationError::MissingChecksum(PackageId::from(&serde)),
+            ValidationError::ResolveVersionMismatch {
+                declared: ResolveVersion::V3,
+                detected: ResolveVersion::V1,
+            },
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "app 0.1.0 depends on a package missing from the lockfile: missing 1.0.0"
+    );
+}
+
+/// Sort packages and dependencies in Cargo's order
+#[test]
+fn sort() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let mut lockfile = original.clone();
+    lockfile.packages.reverse();
+
+    for package in &mut lockfile.packages {
+        package.dependencies.reverse();
+    }
+
+    lockfile.sort();
+    assert_eq!(lockfile, original);
+
+    lockfile.sort();
+    assert_eq!(lockfile, original);
+}
+
+/// Iterate over packages in the order they're serialized in
+#[test]
+fn iter_sorted() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v2-example").unwrap();
+    let mut lockfile = original.clone();
+    lockfile.packages.reverse();
+
+    let sorted = lockfile.iter_sorted().collect::<Vec<_>>();
+    assert!(sorted.iter().copied().eq(&original.packages));
+
+    // Same order as the `[[package]]` entries of the serialized lockfile
+    let serialized = lockfile.to_string();
+    let names = serialized
+        .lines()
+        .filter_map(|line| line.strip_prefix("name = "))
+        .map(|name| name.trim_matches('"'))
+        .collect::<Vec<_>>();
+
+    let sorted_names = sorted
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, sorted_names);
+}
+
+/// Summarize packages the way `cargo lock list` does
+#[test]
+fn summaries() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let summaries = lockfile.summaries();
+    assert_eq!(summaries.len(), lockfile.packages.len());
+
+    for (summary, package) in summaries.iter().zip(&lockfile.packages) {
+        assert!(summary.matches(package));
+        assert_eq!(summary.source, package.source);
+    }
+
+    assert_eq!(summaries[0].to_string(), "app 0.1.0");
+
+    let sourced = lockfile.summaries_filtered(|package| package.source.is_some());
+    assert_eq!(sourced.len(), summaries.len() - 1);
+    assert!(sourced.iter().all(|summary| summary.source.is_some()));
+}
+
+/// Find packages by names differing in case or hyphens and underscores
+#[test]
+fn find_package_fuzzy() {
+    let lockfile = "[[package]]\nname = \"foo-bar\"\nversion = \"1.0.0\"\n\n\
+                    [[package]]\nname = \"foo_bar\"\nversion = \"1.0.0\"\n\n\
+                    [[package]]\nname = \"serde_derive\"\nversion = \"1.0.0\"\n\n\
+                    [[package]]\nname = \"serde_derive\"\nversion = \"2.0.0\"\n"
+        .parse::<Lockfile>()
+        .unwrap();
+
+    let names = |query: &str| {
+        lockfile
+            .find_package_fuzzy(query)
+            .iter()
+            .map(|pkg| format!("{} {}", pkg.name, pkg.version))
+            .collect::<Vec<_>>()
+    };
+
+    // Exact matches take precedence
+    assert_eq!(names("foo-bar"), ["foo-bar 1.0.0"]);
+    assert_eq!(names("Foo-Bar"), ["foo-bar 1.0.0", "foo_bar 1.0.0"]);
+    assert_eq!(
+        names("serde-derive"),
+        ["serde_derive 1.0.0", "serde_derive 2.0.0"]
+    );
+    assert_eq!(names("SERDE_DERIVE").len(), 2);
+    assert!(names("serde").is_empty());
+}
+
+/// `Package` and `Dependency` are ordered the same way as the serializer
+/// emits packages
+#[test]
+fn canonical_ordering() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let original = lockfile.packages.clone();
+    lockfile.packages.reverse();
+
+    let set = lockfile.packages.iter().collect::<BTreeSet<_>>();
+    assert!(set.into_iter().eq(&original));
+
+    let mut dependencies = lockfile.summaries();
+    dependencies.sort();
+    assert!(dependencies
+        .into_iter()
+        .eq(original.iter().map(Dependency::from)));
+
+    lockfile.sort();
+    assert_eq!(lockfile.packages, original);
+}
+
+/// Ensure pretty serialization emits packages in Cargo's canonical order
+#[test]
+fn pretty_serialize_sorts_packages() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v2-example").unwrap();
+    let mut lockfile = original.parse::<Lockfile>().unwrap();
+    lockfile.packages.reverse();
+    assert_eq!(to_string_pretty(&lockfile), original);
+}
+
+/// Serialize with Windows line endings and a byte order mark, which should
+/// still parse back to the same lockfile
+#[test]
+fn serialize_with_crlf_and_bom() {
+    let original = fs::read_to_string("Cargo.lock").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+
+    let options = SerializeOptions {
+        newline: Newline::CrLf,
+        bom: true,
+        ..SerializeOptions::default()
+    };
+
+    let mut output = Vec::new();
+    lockfile
+        .to_writer_with_options(&mut output, &options)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.starts_with("\u{feff}# This file"));
+    assert_eq!(output.matches('\n').count(), output.matches("\r\n").count());
+    assert_eq!(output[3..].replace("\r\n", "\n"), original);
+    assert_eq!(output.parse::<Lockfile>().unwrap(), lockfile);
+}
+
+/// Serialize without blank lines, which should still parse back to the same
+/// lockfile
+#[test]
+fn serialize_compact() {
+    let original = fs::read_to_string("Cargo.lock").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+
+    let options = SerializeOptions {
+        compact: true,
+        ..SerializeOptions::default()
+    };
+
+    let output = lockfile.to_string_with_options(&options);
+    assert!(!output.contains("\n\n"));
+    assert!(output.ends_with("]\n"));
+    assert_eq!(
+        output.lines().count(),
+        original.lines().filter(|l| !l.is_empty()).count()
+    );
+    assert_eq!(output.parse::<Lockfile>().unwrap(), lockfile);
+
+    // The defaults match Cargo's formatting
+    let output = lockfile.to_string_with_options(&SerializeOptions::default());
+    assert_eq!(output, original);
+}
+
+/// Ensure content hashes are independent of formatting and package order
+#[test]
+fn content_hash() {
+    let mut lockfile = Lockfile::load("Cargo.lock").unwrap();
+    let hash = lockfile.content_hash();
+    assert_eq!(hash.len(), 64);
+
+    let reformatted = Lockfile::load("tests/support/Cargo.lock.whitespace-example").unwrap();
+    assert_eq!(reformatted.content_hash(), hash);
+
+    lockfile.packages.reverse();
+    assert_eq!(lockfile.content_hash(), hash);
+
+    lockfile.preamble = Some("# Pinned for the 1.2 release\n".to_owned());
+    assert_eq!(lockfile.content_hash(), hash);
+
+    lockfile.packages.pop();
+    assert_ne!(lockfile.content_hash(), hash);
+}
+
+/// Trivial (and insecure) hash function for testing custom hashers
+#[derive(Default)]
+struct XorHasher(u8);
+
+impl ContentHasher for XorHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.0 = data.iter().fold(self.0, |acc, byte| acc ^ byte);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        vec![self.0]
+    }
+}
+
+/// Compute content hashes using a custom hash function
+#[test]
+fn content_hash_with_custom_hasher() {
+    let lockfile = Lockfile::load("Cargo.lock").unwrap();
+    let mut canonical = Vec::new();
+    lockfile.to_writer_pretty(&mut canonical).unwrap();
+
+    let expected = canonical.iter().fold(0, |acc, byte| acc ^ byte);
+    assert_eq!(
+        lockfile.content_hash_with::<XorHasher>(),
+        format!("{:02x}", expected)
+    );
+}
+
+/// Ensure package IDs are sorted and stable across a serialization round trip
+#[test]
+fn package_ids_are_stable() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v2-example").unwrap();
+    let ids = lockfile.package_ids();
+
+    assert_eq!(ids.len(), lockfile.packages.len());
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(
+        ids[0].to_string(),
+        "adler32 1.0.3 (registry+https://github.com/rust-lang/crates.io-index)"
+    );
+
+    let reparsed = lockfile.to_string().parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed.package_ids(), ids);
+
+    lockfile.packages.reverse();
+    assert_eq!(lockfile.package_ids(), ids);
+}
+
+/// Guess which Cargo versions produced the example lockfiles
+#[test]
+fn likely_cargo_version_range() {
+    let range = |path: &str| Lockfile::load(path).unwrap().likely_cargo_version_range();
+
+    assert_eq!(range("tests/support/Cargo.lock.v1-example"), "< 1.41");
+    assert_eq!(range("tests/support/Cargo.lock.v2-example"), ">= 1.41");
+    assert_eq!(range("tests/support/Cargo.lock.v3-example"), ">= 1.53");
+    assert_eq!(range("tests/support/Cargo.lock.v4-example"), ">= 1.78");
+
+    let sparse = fs::read_to_string("Cargo.lock").unwrap().replace(
+        "registry+https://github.com/rust-lang/crates.io-index",
+        "sparse+https://index.crates.io/",
+    );
+
+    let lockfile = sparse.parse::<Lockfile>().unwrap();
+    assert_eq!(lockfile.likely_cargo_version_range(), ">= 1.68");
+}
+
+/// Iterate over all dependency edges
+#[test]
+fn iter_dependencies() {
+    let lockfile = load_our_lockfile();
+    let edges = lockfile.iter_dependencies().collect::<Vec<_>>();
+    let count = lockfile
+        .packages
+        .iter()
+        .map(|pkg| pkg.dependencies.len())
+        .sum::<usize>();
+
+    assert_eq!(edges.len(), count);
+    assert!(edges
+        .iter()
+        .any(|(pkg, dep)| pkg.name.as_str() == "semver" && dep.name.as_str() == "semver-parser"));
+
+    // Every dependency of our own lockfile refers to a package within it
+    assert!(edges
+        .iter()
+        .all(|(_, dep)| lockfile.packages.iter().any(|pkg| dep.matches(pkg))));
+}
+
+/// Resolve dependencies to the packages they refer to
+#[test]
+fn resolve_dependencies() {
+    let mut lockfile = load_our_lockfile();
+    let semver = lockfile
+        .packages
+        .iter()
+        .find(|pkg| pkg.name.as_str() == "semver")
+        .unwrap()
+        .clone();
+
+    let resolved = lockfile.resolve_dependencies(&semver).unwrap();
+    assert_eq!(resolved.len(), semver.dependencies.len());
+
+    for (package, dependency) in resolved.iter().zip(&semver.dependencies) {
+        assert_eq!(Dependency::from(*package), *dependency);
+    }
+
+    let mut dangling = semver.clone();
+    dangling.dependencies[0].version = Version::parse("99.0.0").unwrap();
+    let err = lockfile.resolve_dependencies(&dangling).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DanglingDependency);
+
+    let parser = lockfile.resolve_dependencies(&semver).unwrap()[0].clone();
+    lockfile.packages.push(parser);
+    let err = lockfile.resolve_dependencies(&semver).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicatePackage);
+}
+
+/// Find the packages nothing else depends on
+#[test]
+fn root_packages() {
+    let lockfile = load_our_lockfile();
+    let roots = lockfile.root_packages().unwrap();
+    assert_eq!(roots.len(), 1);
+    assert_eq!(roots[0].name.as_str(), "cargo-lock");
+
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let roots = lockfile.root_packages().unwrap();
+    assert!(!roots.is_empty());
+    assert!(roots.iter().all(|root| root.source.is_none()));
+
+    let cyclic = "version = 3\n\n\
+                  [[package]]\nname = \"a\"\nversion = \"1.0.0\"\ndependencies = [\"b\"]\n\n\
+                  [[package]]\nname = \"b\"\nversion = \"1.0.0\"\ndependencies = [\"a\"]\n";
+
+    let err = cyclic
+        .parse::<Lockfile>()
+        .unwrap()
+        .root_packages()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingRoots);
+}
+
+/// Find the legacy `[root]` package, unless it's also a `[[package]]`
+#[test]
+fn root_package() {
+    let toml = "[root]\nname = \"app\"\nversion = \"0.1.0\"\n\
+                dependencies = [\"lib 1.0.0\"]\n\n\
+                [[package]]\nname = \"lib\"\nversion = \"1.0.0\"\n";
+
+    let lockfile = toml.parse::<Lockfile>().unwrap();
+    let root = lockfile.root_package().unwrap();
+    assert_eq!(root.name.as_str(), "app");
+    assert_eq!(root.dependencies[0].name.as_str(), "lib");
+    assert_eq!(Some(root), lockfile.root.as_ref());
+
+    // A `[[package]]` entry for the same package takes precedence
+    let mut lockfile = lockfile;
+    let mut package = lockfile.root.clone().unwrap();
+    package.checksum = Some(
+        "414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449"
+            .parse()
+            .unwrap(),
+    );
+    lockfile.packages.push(package.clone());
+    assert_eq!(lockfile.root_package(), Some(&package));
+
+    assert_eq!(load_our_lockfile().root_package(), None);
+}
+
+/// Merge lockfiles, deduplicating shared packages
+#[test]
+fn merge_lockfiles() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let other = Lockfile::load("tests/support/Cargo.lock.source-swap-example").unwrap();
+    lockfile.merge(&other).unwrap();
+
+    // `app` 0.1.0 is present in both, with a union of their dependencies
+    assert_eq!(lockfile.packages.len(), 7);
+    assert_eq!(lockfile.packages[0].name.as_str(), "app");
+    assert_eq!(lockfile.packages[0].dependencies.len(), 6);
+
+    let ids = lockfile.package_ids();
+    assert_eq!(
+        lockfile
+            .packages
+            .iter()
+            .map(PackageId::from)
+            .collect::<Vec<_>>(),
+        ids
+    );
+
+    // Merging again is a no-op
+    let merged = lockfile.clone();
+    lockfile.merge(&other).unwrap();
+    assert_eq!(lockfile, merged);
+}
+
+/// Merging lockfiles without any shared packages concatenates them
+#[test]
+fn merge_disjoint_lockfiles() {
+    let mut lockfile = "[[package]]\nname = \"zzz\"\nversion = \"0.1.0\"\n"
+        .parse::<Lockfile>()
+        .unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V2);
+
+    let other = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    lockfile.merge(&other).unwrap();
+
+    let mut expected = other.packages.clone();
+    expected.push(lockfile.packages[4].clone());
+    assert_eq!(lockfile.packages, expected);
+    assert_eq!(lockfile.packages[4].name.as_str(), "zzz");
+    assert_eq!(lockfile.version, ResolveVersion::V3);
+}
+
+/// Ensure conflicting checksums are an error that leaves the lockfile as-is
+#[test]
+fn merge_conflicting_checksums() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let mut other = lockfile.clone();
+    other.packages[3].checksum = Some(
+        "0000000000000000000000000000000000000000000000000000000000000000"
+            .parse()
+            .unwrap(),
+    );
+
+    let original = lockfile.clone();
+    let err = lockfile.merge(&other).unwrap_err();
+    assert!(err
+        .msg()
+        .starts_with("conflicting checksums for serde 1.0.104"));
+    assert_eq!(err.kind(), ErrorKind::DuplicatePackage);
+    assert_eq!(lockfile, original);
+}
+
+/// Ensure conflicting metadata is an error that leaves the lockfile as-is
+#[test]
+fn merge_conflicting_metadata() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v1-example").unwrap();
+    let mut other = lockfile.clone();
+    let key = other.metadata.keys().next().unwrap().clone();
+    other.metadata.insert(key, "conflicting".parse().unwrap());
+
+    let original = lockfile.clone();
+    let err = lockfile.merge(&other).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicatePackage);
+    assert!(err.msg().starts_with("conflicting values for metadata key"));
+    assert_eq!(lockfile, original);
+}
+
+/// Strip checksums without modifying the original lockfile
+#[test]
+fn without_checksums() {
+    for path in &["Cargo.lock", "tests/support/Cargo.lock.v1-example"] {
+        let lockfile = Lockfile::load(path).unwrap();
+        let stripped = lockfile.without_checksums();
+
+        assert!(lockfile.packages.iter().any(|pkg| pkg.checksum.is_some()));
+        assert!(stripped.packages.iter().all(|pkg| pkg.checksum.is_none()));
+        assert!(stripped.metadata.keys().all(|key| !key.is_checksum()));
+        assert_eq!(stripped.package_ids(), lockfile.package_ids());
+        assert!(lockfile.diff(&stripped).is_empty());
+    }
+}
+
+/// Find packages matching a version requirement, newest first
+#[test]
+fn query() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let serde = lockfile
+        .packages
+        .iter()
+        .find(|package| package.name.as_str() == "serde")
+        .unwrap()
+        .clone();
+
+    for version in &["1.0.120", "2.0.0", "1.0.200-beta.1"] {
+        let mut package = serde.clone();
+        package.version = version.parse().unwrap();
+        lockfile.add_package(package).unwrap();
+    }
+
+    let versions = |req: &str| {
+        lockfile
+            .query(&serde.name, &req.parse().unwrap())
+            .iter()
+            .map(|package| package.version.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(versions("^1.0.100"), ["1.0.120", "1.0.104"]);
+    assert_eq!(versions("*"), ["2.0.0", "1.0.120", "1.0.104"]);
+    assert_eq!(versions("^1.0.200-beta.0"), ["1.0.200-beta.1"]);
+    assert!(versions("^3").is_empty());
+}
+
+/// Group packages by the kind of their source
+#[test]
+fn packages_by_source_kind() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v4-example").unwrap();
+    let packages = lockfile.packages_by_source_kind();
+    let names = |kind| {
+        packages[&kind]
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        packages.keys().copied().collect::<Vec<_>>(),
+        [SourceKind::CratesIo, SourceKind::Git, SourceKind::Path]
+    );
+    assert_eq!(names(SourceKind::CratesIo), ["serde"]);
+    assert_eq!(names(SourceKind::Git), ["feature-branch", "tagged"]);
+    assert_eq!(names(SourceKind::Path), ["app"]);
+}
+
+/// Ensure lockfiles using the git and sparse protocols for crates.io compare
+/// equal after normalization
+#[test]
+fn normalize_crates_io_sources() {
+    let original = fs::read_to_string("Cargo.lock").unwrap();
+    let sparse = original.replace(
+        "registry+https://github.com/rust-lang/crates.io-index",
+        "sparse+https://index.crates.io/",
+    );
+
+    let mut lockfile = original.parse::<Lockfile>().unwrap();
+    let mut sparse_lockfile = sparse.parse::<Lockfile>().unwrap();
+    assert_ne!(lockfile, sparse_lockfile);
+
+    sparse_lockfile.normalize_crates_io_sources();
+    assert_eq!(lockfile, sparse_lockfile);
+
+    lockfile.normalize_crates_io_sources();
+    assert_eq!(lockfile, original.parse::<Lockfile>().unwrap());
+}
+
+/// Load an example V3 `Cargo.lock`, which declares its version explicitly
+#[test]
+fn load_example_v3_lockfile() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V3);
+    assert_eq!(lockfile.packages.len(), 4);
+
+    let git_reference = |index: usize| {
+        lockfile.packages[index]
+            .source
+            .as_ref()
+            .unwrap()
+            .git_reference()
+            .cloned()
+    };
+
+    assert_eq!(git_reference(1), Some(GitReference::DefaultBranch));
+    assert_eq!(
+        git_reference(2),
+        Some(GitReference::Branch("master".into()))
+    );
+    assert_eq!(lockfile.resolve_version_mismatch(), None);
+}
+
+/// Ensure pretty serialization of the example V3 `Cargo.lock` is
+/// byte-identical to Cargo's
+#[test]
+fn pretty_serialize_example_v3_lockfile() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v3-example").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+    assert_eq!(to_string_pretty(&lockfile), original);
+
+    let reparsed = lockfile.to_string().parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed, lockfile);
+}
+
+/// Load an example V4 `Cargo.lock`, whose git references are percent-encoded
+#[test]
+fn load_example_v4_lockfile() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v4-example").unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V4);
+    assert_eq!(lockfile.packages.len(), 4);
+
+    let git_reference = |index: usize| {
+        lockfile.packages[index]
+            .source
+            .as_ref()
+            .unwrap()
+            .git_reference()
+            .cloned()
+    };
+
+    assert_eq!(
+        git_reference(1),
+        Some(GitReference::Branch("feature/io-uring".into()))
+    );
+    assert_eq!(git_reference(3), Some(GitReference::Tag("v0.3.0+1".into())));
+}
+
+/// Ensure pretty serialization of the example V4 `Cargo.lock` is
+/// byte-identical to Cargo's
+#[test]
+fn pretty_serialize_example_v4_lockfile() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.v4-example").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+    assert_eq!(lockfile.to_string(), original);
+    assert_eq!(lockfile.to_string().parse::<Lockfile>().unwrap(), lockfile);
+
+    // Older formats don't percent-encode git references
+    let mut v3 = lockfile.clone();
+    v3.version = ResolveVersion::V3;
+    let v3 = v3.to_string();
+    assert!(v3.contains("version = 3"));
+    assert!(v3.contains("?branch=feature/io-uring#"));
+}
+
+/// Preserve comments at the top of a lockfile across a round trip
+#[test]
+fn preserve_preamble() {
+    let v3 = fs::read_to_string("tests/support/Cargo.lock.v3-example").unwrap();
+    let lockfile = v3.parse::<Lockfile>().unwrap();
+    assert_eq!(lockfile.preamble, None);
+    assert_eq!(lockfile.to_string(), v3);
+
+    let original = format!("# Pinned for the 1.2 release\n{}", v3);
+    let lockfile = original.parse::<Lockfile>().unwrap();
+    assert_eq!(lockfile.to_string(), original);
+
+    // Without a preamble, Cargo's standard header is written
+    let mut lockfile = original.parse::<Lockfile>().unwrap();
+    lockfile.preamble = None;
+    assert_eq!(lockfile.to_string(), v3);
+}
+
+/// Ensure applied and unused patches from multiple sources survive a
+/// round trip with the same structure and ordering
+#[test]
+fn pretty_serialize_patches() {
+    let original = fs::read_to_string("tests/support/Cargo.lock.patch-example").unwrap();
+    let lockfile = original.parse::<Lockfile>().unwrap();
+
+    let unused = lockfile
+        .patch
+        .unused
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(unused, ["qux", "baz", "local"]);
+    assert!(lockfile.patch.unused[1].checksum.is_some());
+    assert!(lockfile.patch.unused[2].source.is_none());
+
+    assert_eq!(to_string_pretty(&lockfile), original);
+    assert_eq!(lockfile.to_string().parse::<Lockfile>().unwrap(), lockfile);
+
+    let mut v2 = lockfile.clone();
+    v2.version = ResolveVersion::V2;
+    let reparsed = to_string_pretty(&v2).parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed.patch, lockfile.patch);
+}
+
+/// Serialize a V3 lockfile as V2, which drops the explicit `master` branch
+#[test]
+fn serialize_v3_to_v2() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    lockfile.version = ResolveVersion::V2;
+
+    let v2 = to_string_pretty(&lockfile);
+    assert!(!v2.contains("version = 3"));
+    assert!(!v2.contains("?branch=master"));
+    assert_eq!(v2.parse::<Lockfile>().unwrap().version, ResolveVersion::V2);
+}
+
+/// Refuse to change the resolve version when that would lose information
+#[test]
+fn set_resolve_version() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let mut lockfile = original.clone();
+
+    lockfile.set_resolve_version(ResolveVersion::V4).unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V4);
+
+    let err = lockfile
+        .set_resolve_version(ResolveVersion::V2)
+        .unwrap_err();
+    assert_eq!(err.from, ResolveVersion::V4);
+    assert_eq!(err.to, ResolveVersion::V2);
+    assert!(matches!(
+        err.losses.as_slice(),
+        [DowngradeLoss::ExplicitMasterBranch(id)] if id.name.as_str() == "master-branch"
+    ));
+    assert_eq!(lockfile.version, ResolveVersion::V4);
+
+    // V4 percent-encodes git references, while older versions store them as-is
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v4-example").unwrap();
+    let err = lockfile
+        .set_resolve_version(ResolveVersion::V3)
+        .unwrap_err();
+    let mut names = err
+        .losses
+        .iter()
+        .map(|loss| match loss {
+            DowngradeLoss::UrlEncodedGitReference(id) => id.name.as_str(),
+            other => panic!("unexpected loss: {}", other),
+        })
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(names, ["feature-branch", "tagged"]);
+    assert_eq!(lockfile.version, ResolveVersion::V4);
+
+    // Without explicit `master` branches, nothing is lost
+    let mut lockfile = load_our_lockfile();
+    lockfile.set_resolve_version(ResolveVersion::V1).unwrap();
+    assert_eq!(lockfile.version, ResolveVersion::V1);
+}
+
+/// Checksums move between `[metadata]` and packages when changing from V1
+/// to V2 and back
+#[test]
+fn set_resolve_version_migrates_checksums() {
+    let original = Lockfile::load("tests/support/Cargo.lock.v1-example").unwrap();
+    let checksums = original.metadata.checksums().count();
+    assert_ne!(checksums, 0);
+
+    let mut lockfile = original.clone();
+    lockfile.set_resolve_version(ResolveVersion::V2).unwrap();
+    assert_eq!(lockfile.metadata.checksums().count(), 0);
+    assert_eq!(
+        lockfile
+            .packages
+            .iter()
+            .filter(|pkg| pkg.checksum.is_some())
+            .count(),
+        checksums
+    );
+
+    let v2 = lockfile.to_string();
+    assert!(!v2.contains("[metadata]"));
+    assert_eq!(v2.parse::<Lockfile>().unwrap(), lockfile);
+
+    lockfile.set_resolve_version(ResolveVersion::V1).unwrap();
+    assert_eq!(lockfile, original);
+    assert_eq!(lockfile.to_string(), original.to_string());
+}
+
+/// Move V1-style checksums out of the `[metadata]` table of a V3 lockfile
+#[test]
+fn inline_metadata_checksums() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.version-mismatch-example").unwrap();
+    assert!(lockfile.packages[1].checksum.is_none());
+
+    assert_eq!(lockfile.inline_metadata_checksums(), 1);
+    assert!(lockfile.packages[1].checksum.is_some());
+    assert!(lockfile.metadata.is_empty());
+    assert_eq!(lockfile.resolve_version_mismatch(), None);
+
+    assert_eq!(lockfile.inline_metadata_checksums(), 0);
+}
+
+/// Find and collapse packages present from both the git and sparse
+/// crates.io index
+#[test]
+fn mixed_crates_io_sources() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.mixed-sources-example").unwrap();
+
+    let pairs = lockfile.mixed_crates_io_sources();
+    assert_eq!(pairs.len(), 1);
+    assert_eq!(pairs[0].0.name.as_str(), "serde");
+    assert!(!pairs[0].0.source.as_ref().unwrap().is_sparse());
+    assert!(pairs[0].1.source.as_ref().unwrap().is_sparse());
+
+    assert_eq!(lockfile.collapse_mixed_crates_io_sources(), 1);
+    assert!(lockfile.mixed_crates_io_sources().is_empty());
+    assert_eq!(lockfile.packages.len(), 3);
+
+    // `serde_derive` now depends on the remaining (git) `serde` package
+    let serde = PackageId::from(&lockfile.packages[1]);
+    assert_eq!(
+        PackageId::from(&lockfile.packages[2].dependencies[0]),
+        serde
+    );
+
+    let reparsed = to_string_pretty(&lockfile).parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed, lockfile);
+    assert_eq!(lockfile.collapse_mixed_crates_io_sources(), 0);
+}
+
+/// Detect and unify the protocols used to access crates.io
+#[test]
+fn crates_io_protocols() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.mixed-sources-example").unwrap();
+    lockfile.collapse_mixed_crates_io_sources();
+    assert_eq!(
+        lockfile
+            .crates_io_protocols()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [CratesIoProtocol::Git, CratesIoProtocol::Sparse]
+    );
+
+    lockfile.unify_crates_io_protocol(CratesIoProtocol::Sparse);
+    assert_eq!(
+        lockfile
+            .crates_io_protocols()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [CratesIoProtocol::Sparse]
+    );
+    assert_eq!(
+        lockfile.packages[2].dependencies[0].source,
+        lockfile.packages[1].source
+    );
+
+    // Other sources are left alone
+    let original = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let mut lockfile = original.clone();
+    lockfile.unify_crates_io_protocol(CratesIoProtocol::Sparse);
+
+    for (package, original) in lockfile.packages.iter().zip(&original.packages) {
+        match original.source.as_ref().map(SourceId::kind) {
+            Some(SourceKind::CratesIo) => assert!(package.source.as_ref().unwrap().is_sparse()),
+            _ => assert_eq!(package.source, original.source),
+        }
+    }
+}
+
+/// Remove a package which another package still depends on, and add it back
+#[test]
+fn add_and_remove_packages() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let original = lockfile.clone();
+    assert!(lockfile.dangling_dependencies().is_empty());
+
+    let name = "serde".parse().unwrap();
+    let version = Version::parse("1.0.104").unwrap();
+    let serde = lockfile.remove_package(&name, &version).unwrap();
+    assert!(lockfile.remove_package(&name, &version).is_none());
+
+    let dangling = lockfile
+        .dangling_dependencies()
+        .into_iter()
+        .map(|(package, dependency)| (package.name.as_str(), dependency.clone()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(dangling, [("app", Dependency::from(&serde))]);
+
+    lockfile.add_package(serde.clone()).unwrap();
+    assert_eq!(lockfile, original);
+    assert_eq!(
+        lockfile.add_package(serde).unwrap_err().kind(),
+        ErrorKind::DuplicatePackage
+    );
+
+    lockfile.remove_package(&name, &version).unwrap();
+    assert_eq!(lockfile.prune_dangling_dependencies(), 1);
+    assert!(lockfile.dangling_dependencies().is_empty());
+    assert_eq!(lockfile.packages[0].dependencies.len(), 2);
+}
+
+/// Compute the transitive dependencies of packages, including in a cycle
+#[test]
+fn transitive_dependencies() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let version = |v: &str| Version::parse(v).unwrap();
+    let names = |deps: BTreeSet<Name>| deps.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    let app = "app".parse::<Name>().unwrap();
+    let deps = lockfile.transitive_dependencies(&app, &version("0.1.0"));
+    assert_eq!(
+        names(deps.unwrap()),
+        ["default-branch", "master-branch", "serde"]
+    );
+
+    // Make `serde` depend on `app`, completing a cycle
+    let app_dependency = Dependency::from(&lockfile.packages[0]);
+    lockfile.packages[3].dependencies.push(app_dependency);
+    let serde = "serde".parse::<Name>().unwrap();
+    let deps = lockfile.transitive_dependencies(&serde, &version("1.0.104"));
+    assert_eq!(
+        names(deps.unwrap()),
+        ["app", "default-branch", "master-branch"]
+    );
+
+    let err = lockfile
+        .transitive_dependencies(&app, &version("9.9.9"))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PackageNotFound);
+}
+
+/// Preview the effect of a `[patch.crates-io]` override
+#[test]
+fn apply_patch() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let serde = lockfile.packages[3].clone();
+    let crates_io = serde.source.clone().unwrap();
+
+    let mut fork = serde.clone();
+    fork.version = "1.0.110".parse().unwrap();
+    fork.source = Some(
+        "git+https://github.com/example/serde#1f2e3d4c5b6a79880716253443526170f8e9d0c1"
+            .parse()
+            .unwrap(),
+    );
+    fork.checksum = None;
+
+    let incompatible = Package {
+        version: "2.0.0".parse().unwrap(),
+        ..fork.clone()
+    };
+    let err = lockfile.apply_patch(&crates_io, &incompatible).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnusedPatch);
+
+    let replaced = lockfile.apply_patch(&crates_io, &fork).unwrap();
+    assert_eq!(replaced, [PackageId::from(&serde)]);
+    assert_eq!(lockfile.packages[3], fork);
+
+    // `app` now depends on the fork
+    assert_eq!(
+        lockfile.packages[0].dependencies[2],
+        Dependency::from(&fork)
+    );
+
+    let reparsed = to_string_pretty(&lockfile).parse::<Lockfile>().unwrap();
+    assert_eq!(reparsed, lockfile);
+
+    // The crates.io index matches regardless of the protocol it's accessed by
+    let sparse = "sparse+https://index.crates.io/".parse().unwrap();
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    assert_eq!(lockfile.apply_patch(&sparse, &fork).unwrap().len(), 1);
+}
+
+/// Find crates with more than one version
+#[test]
+fn find_duplicates() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v2-example").unwrap();
+    let duplicates = lockfile.find_duplicates();
+
+    let hex = &duplicates[&"hex".parse().unwrap()];
+    let versions = hex
+        .iter()
+        .map(|pkg| pkg.version.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["0.3.2", "0.4.0"]);
+
+    for packages in duplicates.values() {
+        assert!(packages
+            .windows(2)
+            .all(|pair| pair[0].version <= pair[1].version));
+        assert_ne!(packages[0].version, packages.last().unwrap().version);
+    }
+
+    // The same version from different sources isn't a duplicate by itself
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.mixed-sources-example").unwrap();
+    assert!(lockfile.find_duplicates().is_empty());
+}
+
+/// Compute metrics about the size and shape of the dependency graph
+#[test]
+fn metrics() {
+    let metrics = Lockfile::load("tests/support/Cargo.lock.v3-example")
+        .unwrap()
+        .metrics();
+
+    assert_eq!(metrics.packages, 4);
+    assert_eq!(metrics.names, 4);
+    assert_eq!(metrics.duplicates, 0);
+    assert_eq!(metrics.max_depth, 1);
+    assert_eq!((metrics.roots, metrics.leaves), (1, 3));
+    assert_eq!(metrics.sources.path, 1);
+    assert_eq!(metrics.sources.registry, 1);
+    assert_eq!(metrics.sources.git, 2);
+
+    let metrics = Lockfile::load("tests/support/Cargo.lock.mixed-sources-example")
+        .unwrap()
+        .metrics();
+
+    assert_eq!((metrics.packages, metrics.names), (4, 3));
+    assert_eq!(metrics.duplicates, 1);
+    assert_eq!(metrics.max_depth, 2);
+    assert_eq!((metrics.roots, metrics.leaves), (1, 2));
+    assert_eq!(metrics.sources.sparse, 2);
+
+    let serialized = toml::to_string(&metrics).unwrap();
+    assert!(serialized.contains("max_depth = 2"));
+}
+
+/// Render packages in the multi-line format intended for diffs
+#[test]
+fn package_display() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+
+    assert_eq!(
+        lockfile.packages[0].to_string(),
+        "\
+name: app
+version: 0.1.0
+source: none
+checksum: none
+dependencies:
+  - default-branch 0.2.0 (git+https://github.com/example/default-branch#3d2c5b6f8e1a4c7d9b0e2f4a6c8d0e1f2a3b4c5d)
+  - master-branch 0.3.0 (git+https://github.com/example/master-branch?branch=master#9f8e7d6c5b4a39281706f5e4d3c2b1a098765432)
+  - serde 1.0.104 (registry+https://github.com/rust-lang/crates.io-index)
+replace: none
+"
+    );
+
+    assert_eq!(
+        lockfile.packages[3].to_string(),
+        "\
+name: serde
+version: 1.0.104
+source: registry+https://github.com/rust-lang/crates.io-index
+checksum: 414115f25f818d7dfccec8ee535d76949ae78584fc4f79a6f45a904bf8ab4449
+dependencies: none
+replace: none
+"
+    );
+}
+
+/// Classify packages as pinned (reproducible) or floating
+#[test]
+fn pinned_packages() {
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    let pinned = |lockfile: &Lockfile| {
+        lockfile
+            .packages
+            .iter()
+            .map(|package| package.is_pinned())
+            .collect::<Vec<_>>()
+    };
+
+    // app (path), default-branch (git), master-branch (git), serde (registry)
+    assert_eq!(pinned(&lockfile), [false, true, true, true]);
+
+    let git = lockfile.packages[1].source.take().unwrap();
+    lockfile.packages[1].source = Some(git.with_precise(None));
+    lockfile.packages[3].checksum = None;
+    assert_eq!(pinned(&lockfile), [false, false, true, false]);
+}
+
+/// Detect lockfiles whose declared version disagrees with their contents
+#[test]
+fn resolve_version_mismatch() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.version-mismatch-example").unwrap();
+    assert_eq!(
+        lockfile.resolve_version_mismatch(),
+        Some((ResolveVersion::V3, ResolveVersion::V1))
+    );
+
+    let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+    lockfile.version = ResolveVersion::V2;
+    assert_eq!(
+        lockfile.resolve_version_mismatch(),
+        Some((ResolveVersion::V2, ResolveVersion::V3))
+    );
+
+    for path in &["Cargo.lock", "tests/support/Cargo.lock.v1-example"] {
+        assert_eq!(
+            Lockfile::load(path).unwrap().resolve_version_mismatch(),
+            None
+        );
+    }
+}
+
 /// Dependency tree tests
 #[cfg(feature = "dependency-tree")]
 mod tree {
-    use super::Lockfile;
+    use super::{load_our_lockfile, ErrorKind, Lockfile, Name, Version};
 
     /// Compute a dependency graph from this crate's own `Cargo.lock`
     #[test]
@@ -121,4 +1521,89 @@ mod tree {
 
         assert_eq!(tree.nodes().len(), 472);
     }
+
+    /// Find packages which are unreachable from the workspace members
+    #[test]
+    fn unused_packages() {
+        let mut lockfile = Lockfile::load("tests/support/Cargo.lock.v3-example").unwrap();
+        assert!(lockfile.unused_packages().unwrap().is_empty());
+
+        let mut orphan = lockfile.packages[3].clone();
+        orphan.name = "orphan".parse().unwrap();
+        lockfile.add_package(orphan.clone()).unwrap();
+
+        let unused = lockfile.unused_packages().unwrap();
+        assert_eq!(unused, [&orphan]);
+
+        // Without workspace members, there's no way to identify roots
+        let version = Version::parse("0.1.0").unwrap();
+        lockfile.remove_package(&"app".parse().unwrap(), &version);
+        assert!(lockfile.unused_packages().is_err());
+    }
+
+    /// Find the packages only needed by a given one
+    #[test]
+    fn packages_removable_with() {
+        let lockfile = load_our_lockfile();
+        let name = |s: &str| s.parse::<Name>().unwrap();
+
+        let removable = lockfile.packages_removable_with(&name("semver")).unwrap();
+        assert_eq!(removable, [name("semver-parser")].iter().cloned().collect());
+
+        // `syn` is still needed by other packages
+        let removable = lockfile
+            .packages_removable_with(&name("serde_derive"))
+            .unwrap();
+        assert!(!removable.contains(&name("syn")));
+
+        // Without the root package, nothing else is needed
+        let removable = lockfile
+            .packages_removable_with(&name("cargo-lock"))
+            .unwrap();
+        assert_eq!(removable.len(), lockfile.packages.len() - 1);
+
+        let err = lockfile
+            .packages_removable_with(&name("nonexistent"))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PackageNotFound);
+    }
+
+    /// Find the dependency paths leading to a package
+    #[test]
+    fn dependency_paths() {
+        let lockfile = Lockfile::load("Cargo.lock").unwrap();
+        let name = |name: &str| name.parse().unwrap();
+
+        assert_eq!(
+            lockfile.dependency_paths(&name("semver-parser")).unwrap(),
+            [[name("cargo-lock"), name("semver"), name("semver-parser")]]
+        );
+        assert!(lockfile
+            .dependency_paths(&name("nonexistent"))
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Find the shortest path between two packages by name
+    #[test]
+    fn find_path() {
+        let lockfile = load_our_lockfile();
+        let names = |from: &str, to: &str| {
+            lockfile
+                .find_path(&from.parse().unwrap(), &to.parse().unwrap())
+                .unwrap()
+                .map(|path| path.iter().map(|pkg| pkg.name.as_str()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            names("cargo-lock", "quote"),
+            Some(vec!["cargo-lock", "serde", "serde_derive", "quote"])
+        );
+        assert_eq!(names("quote", "cargo-lock"), None);
+
+        let err = lockfile
+            .find_path(&"cargo-lock".parse().unwrap(), &"missing".parse().unwrap())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PackageNotFound);
+    }
 }
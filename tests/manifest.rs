@@ -0,0 +1,122 @@
+//! Manifest cross-reference integration test
+
+use cargo_lock::{
+    manifest::{DependencyKind, Unsatisfied},
+    ErrorKind, Lockfile, Manifest,
+};
+
+/// Load the example lockfile with dev and build dependencies, and its manifest
+fn load_dev_build_example() -> (Lockfile, Manifest) {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.dev-build-example").unwrap();
+    let manifest = Manifest::load("tests/support/Cargo.toml.dev-build-example").unwrap();
+    (lockfile, manifest)
+}
+
+/// Get the names of the packages in the given slice
+fn names(packages: &[&cargo_lock::Package]) -> Vec<String> {
+    packages.iter().map(|pkg| pkg.name.to_string()).collect()
+}
+
+/// Exclude both dev and build dependencies of the root package
+#[test]
+fn runtime_dependencies_only() {
+    let (lockfile, manifest) = load_dev_build_example();
+    let packages = lockfile
+        .packages_for_dependency_kinds(&manifest, &[DependencyKind::Normal])
+        .unwrap();
+
+    // `shared` is also a dev dependency, but is still required by `runtime`
+    assert_eq!(names(&packages), &["app", "runtime", "shared"]);
+}
+
+/// Exclude only the dev dependencies of the root package
+#[test]
+fn exclude_dev_dependencies() {
+    let (lockfile, manifest) = load_dev_build_example();
+    let packages = lockfile
+        .packages_for_dependency_kinds(&manifest, &[DependencyKind::Normal, DependencyKind::Build])
+        .unwrap();
+
+    assert_eq!(
+        names(&packages),
+        &["app", "builder", "buildonly", "runtime", "shared"]
+    );
+}
+
+/// Virtual manifests don't identify a root package
+#[test]
+fn virtual_manifest_is_an_error() {
+    let (lockfile, _) = load_dev_build_example();
+    let manifest = "[workspace]\nmembers = [\"app\"]\n"
+        .parse::<Manifest>()
+        .unwrap();
+
+    let err = lockfile
+        .packages_for_dependency_kinds(&manifest, &[DependencyKind::Normal])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingRoots);
+}
+
+/// Report manifest dependencies which aren't satisfied by the lockfile
+#[test]
+fn unsatisfied_dependencies() {
+    let (lockfile, manifest) = load_dev_build_example();
+    assert!(lockfile.unsatisfied_dependencies(&manifest).is_empty());
+
+    let manifest = r#"
+        [package]
+        name = "app"
+
+        [dependencies]
+        runtime = "1"
+        rt = { version = "1.0", package = "runtime" }
+        newer = { version = "2", package = "runtime" }
+        missing = "1"
+        inherited = { workspace = true }
+    "#
+    .parse::<Manifest>()
+    .unwrap();
+
+    let unsatisfied = lockfile
+        .unsatisfied_dependencies(&manifest)
+        .into_iter()
+        .map(|(dependency, reason)| (dependency.name.as_str(), reason))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        unsatisfied,
+        [
+            ("inherited", Unsatisfied::WorkspaceInherited),
+            ("missing", Unsatisfied::Missing),
+            ("newer", Unsatisfied::Missing),
+        ]
+    );
+}
+
+/// Detect packages overridden by `[patch]` entries
+#[test]
+fn patched_packages() {
+    let lockfile = Lockfile::load("tests/support/Cargo.lock.patch-example").unwrap();
+    let manifest = r#"
+        [package]
+        name = "app"
+
+        [dependencies]
+        bar = { path = "../bar" }
+        baz = "1"
+        foo = "1"
+
+        [patch.crates-io]
+        foo = { git = "https://github.com/example/foo", branch = "fixes" }
+        qux = { git = "https://github.com/example/qux" }
+    "#
+    .parse::<Manifest>()
+    .unwrap();
+
+    let is_patched = |name: &str| lockfile.is_patched(&manifest, &name.parse().unwrap());
+    assert!(is_patched("foo"));
+    assert!(!is_patched("baz"));
+
+    // Listed under `[[patch.unused]]`
+    assert!(!is_patched("qux"));
+}